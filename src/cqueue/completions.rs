@@ -0,0 +1,53 @@
+use std::sync::atomic::Ordering;
+use crate::io_uring;
+use crate::cqueue::CQueue;
+
+/* Reaps every completion available when created, advancing the head once on drop */
+#[derive(Debug)]
+pub struct Completions<'a, T: Sized> {
+  cq:   &'a mut CQueue<T>,
+  head: u32,
+  tail: u32,
+}
+
+impl<'a, T: Sized> Completions<'a, T> {
+  pub(crate) fn new(cq: &'a mut CQueue<T>) -> Completions<'a, T> {
+    let head = unsafe { (*cq.khead).load(Ordering::Acquire) };
+    let tail = unsafe { (*cq.ktail).load(Ordering::Acquire) };
+
+    Completions {
+      cq: cq,
+      head: head,
+      tail: tail,
+    }
+  }
+}
+
+impl<'a, T: Sized> Iterator for Completions<'a, T> {
+  type Item = io_uring::cqe<T>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    while self.head != self.tail {
+      let head = self.head;
+      let cqe = self.cq.get(head);
+
+      self.head = head.wrapping_add(1);
+
+      if self.cq.resolve(cqe, head, self.tail) {
+        return Some(unsafe { cqe.read() });
+      }
+    }
+
+    None
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (0, Some(self.tail.wrapping_sub(self.head) as usize))
+  }
+}
+
+impl<'a, T: Sized> Drop for Completions<'a, T> {
+  fn drop(&mut self) {
+    unsafe { (*self.cq.khead).store(self.head, Ordering::Release) };
+  }
+}
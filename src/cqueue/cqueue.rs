@@ -1,7 +1,20 @@
-use std::mem::size_of;
 use core::ffi::c_void;
 use std::sync::atomic::{AtomicU32, Ordering};
+use libc::{ETIME, ECANCELED};
 use crate::io_uring::{self, *};
+use crate::cqueue::{Completions, CqeGuard};
+
+/* cqe->user_data reserved for internal timeouts, the low bits holding the id */
+pub(crate) const UDATA_DEADLINE: u64 = 0xFFFF_FFFF_0000_0000;
+
+/* Pairs a request with the link timeout bounding it, kept until the timeout's own cqe is reaped */
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+  id:        u32,
+  user_data: u64,
+  fired:     bool,
+  done:      bool,
+}
 
 #[derive(Debug, Clone)]
 pub struct CQueue<T: Sized> {
@@ -12,6 +25,10 @@ pub struct CQueue<T: Sized> {
   pub(crate) cqes:         *mut io_uring::cqe<T>,
   pub(crate) ring_mask:    u32,
   pub(crate) ring_entries: u32,
+  pub(crate) deadlines:    Vec<Deadline>,
+  pub(crate) deadline_id:  u32,
+  pub(crate) waits:        Vec<u64>,
+  pub(crate) resolved:     Option<u32>,
 }
 
 impl<T: Sized> CQueue<T> {
@@ -24,6 +41,10 @@ impl<T: Sized> CQueue<T> {
       cqes: ring.add(p.cq_off.cqes as usize)          as *mut io_uring::cqe<T>,
       ring_mask: ring.add(p.cq_off.ring_mask as usize).cast::<u32>().read(),
       ring_entries: ring.add(p.cq_off.ring_entries as usize).cast::<u32>().read(),
+      deadlines: Vec::new(),
+      deadline_id: 0,
+      waits: Vec::new(),
+      resolved: None,
     }
   }
 
@@ -36,11 +57,34 @@ impl<T: Sized> CQueue<T> {
   }
 
   #[inline]
-  pub(crate) fn needs_flush(&self) -> bool {
-    let flags = IORING_SQ_CQ_OVERFLOW | IORING_SQ_TASKRUN;
+  pub(crate) fn tail(&self) -> u32 {
+    unsafe { (*self.ktail).load(Ordering::Acquire) }
+  }
+
+  #[inline]
+  pub(crate) fn is_full(&self) -> bool {
+    self.available() >= self.ring_entries
+  }
+
+  #[inline]
+  pub(crate) fn overflow(&self) -> u32 {
+    unsafe { (*self.koverflow).load(Ordering::Acquire) }
+  }
 
+  #[inline]
+  pub(crate) fn eventfd_disabled(&self) -> bool {
     unsafe {
-      ((*self.kflags).load(Ordering::Acquire) & flags) > 0
+      ((*self.kflags).load(Ordering::Acquire) & IORING_CQ_EVENTFD_DISABLED) > 0
+    }
+  }
+
+  pub(crate) fn set_eventfd_disabled(&mut self, disabled: bool) {
+    unsafe {
+      if disabled {
+        (*self.kflags).fetch_or(IORING_CQ_EVENTFD_DISABLED, Ordering::Release);
+      } else {
+        (*self.kflags).fetch_and(!IORING_CQ_EVENTFD_DISABLED, Ordering::Release);
+      }
     }
   }
 
@@ -51,16 +95,173 @@ impl<T: Sized> CQueue<T> {
     };
   }
 
-  pub(crate) fn next(&mut self) -> Option<*mut io_uring::cqe<T>> {
-    let shift = if size_of::<cqe<T>>() == 32 { 1 } else { 0 };
+  #[inline]
+  pub(crate) fn get(&self, index: u32) -> *mut io_uring::cqe<T> {
+    /* cqe<T> already spans 32 bytes with CQE32, so no extra stride */
+    unsafe { self.cqes.add((index & self.ring_mask) as usize) }
+  }
+
+  /* Counts ready completions, excluding internal timeouts */
+  pub(crate) fn pending(&self) -> u32 {
     let tail = unsafe { (*self.ktail).load(Ordering::Acquire) };
-    let head = unsafe { (*self.khead).load(Ordering::Acquire) };
-    let index = (head & self.ring_mask) << shift;
-    
-    if tail - head == 0 {
-      return None;
+    let mut head = unsafe { (*self.khead).load(Ordering::Acquire) };
+    let mut ready = 0;
+
+    while head != tail {
+      let user_data = unsafe { (*self.get(head)).user_data };
+
+      if !self.is_deadline(user_data) && !self.waits.contains(&user_data) {
+        ready += 1;
+      }
+      head = head.wrapping_add(1);
+    }
+
+    ready
+  }
+
+  /* Whether the cqe for user_data has been posted but not yet reaped */
+  pub(crate) fn posted(&self, user_data: u64) -> bool {
+    let tail = unsafe { (*self.ktail).load(Ordering::Acquire) };
+    let mut head = unsafe { (*self.khead).load(Ordering::Acquire) };
+
+    while head != tail {
+      if unsafe { (*self.get(head)).user_data } == user_data {
+        return true;
+      }
+      head = head.wrapping_add(1);
+    }
+
+    false
+  }
+
+  pub(crate) fn next(&mut self) -> Option<*mut io_uring::cqe<T>> {
+    loop {
+      let tail = unsafe { (*self.ktail).load(Ordering::Acquire) };
+      let head = unsafe { (*self.khead).load(Ordering::Acquire) };
+
+      if tail.wrapping_sub(head) == 0 {
+        return None;
+      }
+
+      let cqe = self.get(head);
+
+      /* Internal timeouts are never handed out */
+      if self.resolve(cqe, head, tail) {
+        return Some(cqe);
+      }
+      self.advance(1);
     }
+  }
+
+  fn next_id(&mut self) -> u32 {
+    let id = self.deadline_id;
+
+    /* The all ones id is LIBURING_UDATA_TIMEOUT */
+    self.deadline_id = self.deadline_id.wrapping_add(1) % u32::MAX;
+
+    id
+  }
+
+  /* Tracks a request bounded by a link timeout, returning the user_data for that timeout */
+  pub(crate) fn add_deadline(&mut self, user_data: u64) -> u64 {
+    let id = self.next_id();
+
+    self.deadlines.push(Deadline { id: id, user_data: user_data, fired: false, done: false });
+
+    UDATA_DEADLINE | id as u64
+  }
+
+  /* Tracks an internal timeout or removal whose cqe is swallowed whenever it arrives, returning its user_data */
+  pub(crate) fn add_wait(&mut self) -> u64 {
+    let user_data = UDATA_DEADLINE | self.next_id() as u64;
+
+    self.waits.push(user_data);
+
+    user_data
+  }
+
+  /* Forgets a wait whose sqe was never queued */
+  pub(crate) fn drop_wait(&mut self, user_data: u64) {
+    self.waits.retain(|&w| w != user_data);
+  }
+
+  /* Whether user_data belongs to a link timeout added by add_deadline, anything else in the reserved range is the caller's */
+  #[inline]
+  pub(crate) fn is_deadline(&self, user_data: u64) -> bool {
+    user_data >= UDATA_DEADLINE && self.deadlines.iter().any(|d| UDATA_DEADLINE | d.id as u64 == user_data)
+  }
+
+  /* Consumes internal cqes and turns a request cancelled by its deadline into -ETIME, returning whether cqe is handed out */
+  pub(crate) fn resolve(&mut self, cqe: *mut io_uring::cqe<T>, head: u32, tail: u32) -> bool {
+    let (user_data, res) = unsafe { ((*cqe).user_data, (*cqe).res) };
+
+    if let Some(i) = self.waits.iter().position(|&w| w == user_data) {
+      self.waits.swap_remove(i);
+      return false;
+    }
+    if user_data >= UDATA_DEADLINE {
+      let id = (user_data & u32::MAX as u64) as u32;
+
+      /* Anything but -ETIME means the request finished first or the timeout was cancelled along with it */
+      if let Some(i) = self.deadlines.iter().position(|d| d.id == id) {
+        if res == -ETIME && !self.deadlines[i].done {
+          self.deadlines[i].fired = true;
+        } else {
+          self.deadlines.remove(i);
+        }
+        return false;
+      }
+      return true;
+    }
+    /* A cqe left at the head is resolved again by the next call, which must not pair it twice */
+    if res != -ECANCELED || self.resolved == Some(head) {
+      return true;
+    }
+    self.resolved = Some(head);
+
+    /* Requests may share user_data, so pair with a deadline whose timeout already fired, then with one whose timeout cqe follows */
+    let candidate = |d: &Deadline| d.user_data == user_data && !d.done;
+    let mut paired = self.deadlines.iter().position(|d| candidate(d) && d.fired);
+    let mut index = head.wrapping_add(1);
+
+    while paired.is_none() && index != tail {
+      let next = unsafe { &*self.get(index) };
+
+      if next.res == -ETIME && next.user_data >= UDATA_DEADLINE {
+        paired = self.deadlines.iter().position(|d| candidate(d) && UDATA_DEADLINE | d.id as u64 == next.user_data);
+      }
+      index = index.wrapping_add(1);
+    }
+
+    match paired {
+      /* A timeout already reaped has nothing left to swallow, otherwise wait for its cqe before forgetting it */
+      Some(i) => {
+        unsafe { (*cqe).res = -ETIME };
+
+        if self.deadlines[i].fired {
+          self.deadlines.remove(i);
+        } else {
+          self.deadlines[i].done = true;
+        }
+      },
+      /* Cancelled by something else, or its timeout has yet to post, so the oldest such deadline expires with its timeout */
+      None => {
+        if let Some(d) = self.deadlines.iter_mut().find(|d| candidate(d)) {
+          d.done = true;
+        }
+      },
+    };
+
+    true
+  }
+
+  pub(crate) fn guard(&mut self) -> Option<CqeGuard<'_, T>> {
+    let cqe = self.next()?;
+
+    Some(CqeGuard::new(self, cqe))
+  }
 
-    Some(unsafe { self.cqes.add(index as usize) })
+  pub(crate) fn completions(&mut self) -> Completions<'_, T> {
+    Completions::new(self)
   }
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use crate::io_uring;
+use crate::cqueue::CQueue;
+
+/* Borrows the cqe at the head, advancing the head once dropped */
+#[derive(Debug)]
+pub struct CqeGuard<'a, T: Sized> {
+  cq:  &'a mut CQueue<T>,
+  cqe: *mut io_uring::cqe<T>,
+}
+
+impl<'a, T: Sized> CqeGuard<'a, T> {
+  pub(crate) fn new(cq: &'a mut CQueue<T>, cqe: *mut io_uring::cqe<T>) -> CqeGuard<'a, T> {
+    CqeGuard {
+      cq: cq,
+      cqe: cqe,
+    }
+  }
+
+  /* Leaves the cqe in the ring, the caller must advance with Ring::next once done */
+  pub fn keep(self) -> &'a mut io_uring::cqe<T> {
+    let cqe = self.cqe;
+
+    mem::forget(self);
+
+    unsafe { cqe.as_mut().unwrap() }
+  }
+}
+
+impl<'a, T: Sized> Deref for CqeGuard<'a, T> {
+  type Target = io_uring::cqe<T>;
+
+  fn deref(&self) -> &Self::Target {
+    unsafe { &*self.cqe }
+  }
+}
+
+impl<'a, T: Sized> DerefMut for CqeGuard<'a, T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    unsafe { &mut *self.cqe }
+  }
+}
+
+impl<'a, T: Sized> Drop for CqeGuard<'a, T> {
+  fn drop(&mut self) {
+    self.cq.advance(1);
+  }
+}
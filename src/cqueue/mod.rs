@@ -1,5 +1,9 @@
 mod cqueue;
+mod completions;
+mod guard;
 
-pub(crate) use {
-  cqueue::CQueue,
-};
\ No newline at end of file
+pub(crate) use cqueue::CQueue;
+pub use {
+  completions::Completions,
+  guard::CqeGuard,
+};
@@ -5,6 +5,11 @@
  */
 pub const IORING_FILE_INDEX_ALLOC: u32 = !0;
 
+/*
+ * cqe->user_data reserved for internal timeout sqes
+ */
+pub const LIBURING_UDATA_TIMEOUT: u64 = !0;
+
 /*
  * sqe->flags
  */
@@ -93,6 +98,14 @@ pub const IORING_OP_LAST: u32            = 49;
  */
 pub const IORING_URING_CMD_FIXED: u32 = 1 << 0;
 
+/*
+ * sqe->cmd_op for socket uring_cmd
+ */
+pub const SOCKET_URING_OP_SIOCINQ: u32    = 0;
+pub const SOCKET_URING_OP_SIOCOUTQ: u32   = 1;
+pub const SOCKET_URING_OP_GETSOCKOPT: u32 = 2;
+pub const SOCKET_URING_OP_SETSOCKOPT: u32 = 3;
+
 /*
  * sqe->fsync_flags
  */
@@ -168,6 +181,7 @@ pub const IORING_CQE_F_BUFFER: u32       = 1 << 0;
 pub const IORING_CQE_F_MORE: u32         = 1 << 1;
 pub const IORING_CQE_F_SOCK_NOEMPTY: u32 = 1 << 2;
 pub const IORING_CQE_F_NOTIF: u32        = 1 << 3;
+pub const IORING_CQE_F_BUF_MORE: u32     = 1 << 4;
 
 /*
  *
@@ -280,6 +294,7 @@ pub const IO_URING_OP_SUPPORTED: u32 = 1 << 0;
  *
  */
 pub const IOU_PBUF_RING_MMAP: u32 = 1;
+pub const IOU_PBUF_RING_INC: u32  = 2;
 
 /*
  * io_uring_restriction->opcode values
@@ -288,4 +303,14 @@ pub const IORING_RESTRICTION_REGISTER_OP: u32        = 0;
 pub const IORING_RESTRICTION_SQE_OP: u32             = 1;
 pub const IORING_RESTRICTION_SQE_FLAGS_ALLOWED: u32  = 2;
 pub const IORING_RESTRICTION_SQE_FLAGS_REQUIRED: u32 = 3;
-pub const IORING_RESTRICTION_LAST: u32               = 4;
\ No newline at end of file
+pub const IORING_RESTRICTION_LAST: u32               = 4;
+
+/*
+ * open_how->resolve flags
+ */
+pub const RESOLVE_NO_XDEV: u64       = 0x01;
+pub const RESOLVE_NO_MAGICLINKS: u64 = 0x02;
+pub const RESOLVE_NO_SYMLINKS: u64   = 0x04;
+pub const RESOLVE_BENEATH: u64       = 0x08;
+pub const RESOLVE_IN_ROOT: u64       = 0x10;
+pub const RESOLVE_CACHED: u64        = 0x20;
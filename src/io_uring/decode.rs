@@ -0,0 +1,299 @@
+use std::fmt;
+use std::ptr;
+use std::io::Error;
+use libc::*;
+use super::definitions::*;
+use super::constants::*;
+
+/* Human readable rendering of sqes and cqes for debugging */
+const OPCODES: [&str; IORING_OP_LAST as usize] = [
+  "NOP", "READV", "WRITEV", "FSYNC", "READ_FIXED", "WRITE_FIXED", "POLL_ADD", "POLL_REMOVE",
+  "SYNC_FILE_RANGE", "SENDMSG", "RECVMSG", "TIMEOUT", "TIMEOUT_REMOVE", "ACCEPT", "ASYNC_CANCEL",
+  "LINK_TIMEOUT", "CONNECT", "FALLOCATE", "OPENAT", "CLOSE", "FILES_UPDATE", "STATX", "READ",
+  "WRITE", "FADVISE", "MADVISE", "SEND", "RECV", "OPENAT2", "EPOLL_CTL", "SPLICE",
+  "PROVIDE_BUFFERS", "REMOVE_BUFFERS", "TEE", "SHUTDOWN", "RENAMEAT", "UNLINKAT", "MKDIRAT",
+  "SYMLINKAT", "LINKAT", "MSG_RING", "FSETXATTR", "SETXATTR", "FGETXATTR", "GETXATTR", "SOCKET",
+  "URING_CMD", "SEND_ZC", "SENDMSG_ZC",
+];
+
+const SQE_FLAGS: [(u32, &str); 6] = [
+  (IOSQE_FIXED_FILE,       "fixed_file"),
+  (IOSQE_IO_DRAIN,         "drain"),
+  (IOSQE_IO_LINK,          "link"),
+  (IOSQE_IO_HARDLINK,      "hardlink"),
+  (IOSQE_ASYNC,            "async"),
+  (IOSQE_CQE_SKIP_SUCCESS, "cqe_skip"),
+];
+
+const MSG_FLAGS: [(c_int, &str); 11] = [
+  (MSG_OOB,          "MSG_OOB"),
+  (MSG_PEEK,         "MSG_PEEK"),
+  (MSG_DONTROUTE,    "MSG_DONTROUTE"),
+  (MSG_CTRUNC,       "MSG_CTRUNC"),
+  (MSG_TRUNC,        "MSG_TRUNC"),
+  (MSG_DONTWAIT,     "MSG_DONTWAIT"),
+  (MSG_EOR,          "MSG_EOR"),
+  (MSG_WAITALL,      "MSG_WAITALL"),
+  (MSG_NOSIGNAL,     "MSG_NOSIGNAL"),
+  (MSG_MORE,         "MSG_MORE"),
+  (MSG_CMSG_CLOEXEC, "MSG_CMSG_CLOEXEC"),
+];
+
+pub fn opcode_name(opcode: u8) -> Option<&'static str> {
+  OPCODES.get(opcode as usize).copied()
+}
+
+pub fn errno_name(errno: i32) -> Option<&'static str> {
+  Some(match errno {
+    EPERM        => "EPERM",
+    ENOENT       => "ENOENT",
+    EINTR        => "EINTR",
+    EIO          => "EIO",
+    ENXIO        => "ENXIO",
+    E2BIG        => "E2BIG",
+    EBADF        => "EBADF",
+    EAGAIN       => "EAGAIN",
+    ENOMEM       => "ENOMEM",
+    EACCES       => "EACCES",
+    EFAULT       => "EFAULT",
+    EBUSY        => "EBUSY",
+    EEXIST       => "EEXIST",
+    EXDEV        => "EXDEV",
+    ENODEV       => "ENODEV",
+    ENOTDIR      => "ENOTDIR",
+    EISDIR       => "EISDIR",
+    EINVAL       => "EINVAL",
+    ENFILE       => "ENFILE",
+    EMFILE       => "EMFILE",
+    EFBIG        => "EFBIG",
+    ENOSPC       => "ENOSPC",
+    ESPIPE       => "ESPIPE",
+    EROFS        => "EROFS",
+    EPIPE        => "EPIPE",
+    ERANGE       => "ERANGE",
+    ENAMETOOLONG => "ENAMETOOLONG",
+    ENOSYS       => "ENOSYS",
+    ENOTEMPTY    => "ENOTEMPTY",
+    ELOOP        => "ELOOP",
+    ENODATA      => "ENODATA",
+    ETIME        => "ETIME",
+    EOVERFLOW    => "EOVERFLOW",
+    EBADFD       => "EBADFD",
+    ENOTSOCK     => "ENOTSOCK",
+    EMSGSIZE     => "EMSGSIZE",
+    EOPNOTSUPP   => "EOPNOTSUPP",
+    EAFNOSUPPORT => "EAFNOSUPPORT",
+    EADDRINUSE   => "EADDRINUSE",
+    ENETUNREACH  => "ENETUNREACH",
+    ECONNABORTED => "ECONNABORTED",
+    ECONNRESET   => "ECONNRESET",
+    ENOBUFS      => "ENOBUFS",
+    EISCONN      => "EISCONN",
+    ENOTCONN     => "ENOTCONN",
+    ETIMEDOUT    => "ETIMEDOUT",
+    ECONNREFUSED => "ECONNREFUSED",
+    EHOSTUNREACH => "EHOSTUNREACH",
+    EALREADY     => "EALREADY",
+    EINPROGRESS  => "EINPROGRESS",
+    ECANCELED    => "ECANCELED",
+    _            => return None,
+  })
+}
+
+fn write_msg_flags(f: &mut fmt::Formatter<'_>, flags: i32) -> fmt::Result {
+  let mut rest = flags;
+  let mut sep = " flags=";
+
+  if flags == 0 {
+    return Ok(());
+  }
+  for (flag, name) in MSG_FLAGS {
+    if rest & flag != 0 {
+      write!(f, "{}{}", sep, name)?;
+      rest &= !flag;
+      sep = "|";
+    }
+  }
+  if rest != 0 {
+    write!(f, "{}{:#x}", sep, rest)?;
+  }
+
+  Ok(())
+}
+
+fn write_sqe<T: Sized>(f: &mut fmt::Formatter<'_>, sqe: &sqe<T>) -> fmt::Result {
+  let fd = sqe.fd;
+  let off = sqe.addr2 as i64;
+
+  match opcode_name(sqe.opcode) {
+    Some(name) => write!(f, "{}", name)?,
+    None       => write!(f, "OP({})", sqe.opcode)?,
+  };
+  match sqe.opcode as u32 {
+    IORING_OP_NOP => (),
+    IORING_OP_READV | IORING_OP_WRITEV => {
+      write!(f, " fd={} iov={:#x} iovcnt={} off={}", fd, sqe.addr1, sqe.len, off)?;
+    },
+    IORING_OP_READ | IORING_OP_WRITE | IORING_OP_READ_FIXED | IORING_OP_WRITE_FIXED => {
+      write!(f, " fd={} buf={:#x} len={} off={}", fd, sqe.addr1, sqe.len, off)?;
+    },
+    IORING_OP_SEND | IORING_OP_RECV | IORING_OP_SEND_ZC => {
+      write!(f, " fd={} buf={:#x} len={}", fd, sqe.addr1, sqe.len)?;
+      write_msg_flags(f, sqe.op_flags)?;
+    },
+    IORING_OP_SENDMSG | IORING_OP_RECVMSG | IORING_OP_SENDMSG_ZC => {
+      write!(f, " fd={} msg={:#x}", fd, sqe.addr1)?;
+      write_msg_flags(f, sqe.op_flags)?;
+    },
+    IORING_OP_ACCEPT => {
+      write!(f, " fd={} addr={:#x} addrlen={:#x}", fd, sqe.addr1, sqe.addr2)?;
+    },
+    IORING_OP_CONNECT => {
+      write!(f, " fd={} addr={:#x} addrlen={}", fd, sqe.addr1, sqe.addr2)?;
+    },
+    IORING_OP_OPENAT => {
+      write!(f, " dirfd={} path={:#x} flags={:#o} mode={:#o}", fd, sqe.addr1, sqe.op_flags, sqe.len)?;
+    },
+    IORING_OP_OPENAT2 => {
+      write!(f, " dirfd={} path={:#x} how={:#x} size={}", fd, sqe.addr1, sqe.addr2, sqe.len)?;
+    },
+    IORING_OP_CLOSE | IORING_OP_FSYNC | IORING_OP_SHUTDOWN => {
+      write!(f, " fd={}", fd)?;
+    },
+    _ => {
+      write!(f, " fd={} addr1={:#x} addr2={:#x} len={} op_flags={:#x}", fd, sqe.addr1, sqe.addr2, sqe.len, sqe.op_flags)?;
+    },
+  };
+  if sqe.ioprio != 0 {
+    write!(f, " ioprio={:#x}", sqe.ioprio)?;
+  }
+  if sqe.user_data != 0 {
+    write!(f, " user_data={}", sqe.user_data)?;
+  }
+  for (flag, name) in SQE_FLAGS {
+    if sqe.flags as u32 & flag != 0 {
+      write!(f, " {}", name)?;
+    }
+  }
+  if sqe.flags as u32 & IOSQE_BUFFER_SELECT != 0 {
+    write!(f, " buffer_select({})", sqe.buf_select)?;
+  }
+
+  Ok(())
+}
+
+fn write_cqe<T: Sized>(f: &mut fmt::Formatter<'_>, cqe: &cqe<T>) -> fmt::Result {
+  write!(f, "user_data={} res=", cqe.user_data)?;
+
+  match cqe.res {
+    res if res < 0 => match errno_name(-res) {
+      Some(name) => write!(f, "-{}", name)?,
+      None       => write!(f, "{}", res)?,
+    },
+    res => write!(f, "{}", res)?,
+  };
+  if cqe.flags != 0 {
+    let flags = [
+      (IORING_CQE_F_MORE,          "MORE"),
+      (IORING_CQE_F_SOCK_NOEMPTY,  "SOCK_NONEMPTY"),
+      (IORING_CQE_F_NOTIF,         "NOTIF"),
+      (IORING_CQE_F_BUF_MORE,      "BUF_MORE"),
+    ];
+    let mut rest = cqe.flags;
+    let mut sep = " flags=";
+
+    /* The buffer id takes the bits above the shift */
+    if cqe.flags & IORING_CQE_F_BUFFER != 0 {
+      rest &= !IORING_CQE_F_BUFFER & ((1 << IORING_CQE_BUFFER_SHIFT) - 1);
+    }
+    for (flag, name) in flags {
+      if rest & flag != 0 {
+        write!(f, "{}{}", sep, name)?;
+        rest &= !flag;
+        sep = "|";
+      }
+    }
+    if rest != 0 {
+      write!(f, "{}{:#x}", sep, rest)?;
+      sep = "|";
+    }
+    if cqe.flags & IORING_CQE_F_BUFFER != 0 {
+      write!(f, "{}BUFFER({})", sep, cqe.flags >> IORING_CQE_BUFFER_SHIFT)?;
+    }
+  }
+
+  Ok(())
+}
+
+impl<T: Sized> fmt::Display for sqe<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_sqe(f, self)
+  }
+}
+
+/* {:?} prints the decoded form, {:#?} prints the raw fields */
+impl<T: Sized + fmt::Debug> fmt::Debug for sqe<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if !f.alternate() {
+      return write_sqe(f, self);
+    }
+
+    f.debug_struct("sqe")
+      .field("opcode", &self.opcode)
+      .field("flags", &self.flags)
+      .field("ioprio", &self.ioprio)
+      .field("fd", &self.fd)
+      .field("addr2", &self.addr2)
+      .field("addr1", &self.addr1)
+      .field("len", &self.len)
+      .field("op_flags", &self.op_flags)
+      .field("user_data", &self.user_data)
+      .field("buf_select", &self.buf_select)
+      .field("personality", &self.personality)
+      .field("file_select", &self.file_select)
+      .field("addr3", &self.addr3)
+      .finish()
+  }
+}
+
+impl<T: Sized> fmt::Display for cqe<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_cqe(f, self)
+  }
+}
+
+/* {:?} prints the decoded form, {:#?} prints the raw fields */
+impl<T: Sized + fmt::Debug> fmt::Debug for cqe<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if !f.alternate() {
+      return write_cqe(f, self);
+    }
+
+    f.debug_struct("cqe")
+      .field("user_data", &self.user_data)
+      .field("res", &self.res)
+      .field("flags", &self.flags)
+      .field("big_cqe", &self.big_cqe)
+      .finish()
+  }
+}
+
+/* Decodes a raw 64 or 128 byte sqe */
+pub fn decode_sqe(raw: &[u8]) -> Result<String, Error> {
+  let sqe = match raw.len() {
+    64 | 128 => unsafe { ptr::read_unaligned(raw.as_ptr() as *const sqe<[u64; 2]>) },
+    _        => return Err(Error::from_raw_os_error(EINVAL)),
+  };
+
+  Ok(sqe.to_string())
+}
+
+/* Decodes a raw 16 or 32 byte cqe */
+pub fn decode_cqe(raw: &[u8]) -> Result<String, Error> {
+  let cqe = match raw.len() {
+    16 | 32 => unsafe { ptr::read_unaligned(raw.as_ptr() as *const cqe<[u8; 0]>) },
+    _       => return Err(Error::from_raw_os_error(EINVAL)),
+  };
+
+  Ok(cqe.to_string())
+}
\ No newline at end of file
@@ -8,7 +8,7 @@ pub struct __kernel_timespec {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct sqe<T: Sized> {
   pub opcode:      u8,
   pub flags:       u8,
@@ -26,7 +26,7 @@ pub struct sqe<T: Sized> {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct cqe<T: Sized> {
   pub user_data: u64,
   pub res:       i32,
@@ -203,6 +203,14 @@ pub struct file_index_range {
   pub resv: u64,
 }
 
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct open_how {
+  pub flags:   u64,
+  pub mode:    u64,
+  pub resolve: u64,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct recvmsg_out {
@@ -1,5 +1,5 @@
 use core::ffi::{c_void, c_size_t, c_int, c_uint};
-use std::{io::Error, mem::size_of};
+use std::{io::Error, mem::size_of, time::Duration};
 use libc::{syscall, sigset_t, SYS_io_uring_setup, SYS_io_uring_enter, SYS_io_uring_register};
 use super::definitions::*;
 use super::constants::*;
@@ -37,6 +37,17 @@ pub fn register(fd: c_int, opcode: c_uint, arg: *mut c_void, nr_args: c_uint) ->
   if r < 0 { Err(Error::last_os_error()) } else { Ok(r as c_int) }
 }
 
+impl open_how {
+  /* resolve takes the RESOLVE_* flags restricting how the path is walked */
+  pub fn new(flags: c_int, mode: u32, resolve: u64) -> open_how {
+    open_how {
+      flags:   flags as u32 as u64,
+      mode:    mode as u64,
+      resolve: resolve,
+    }
+  }
+}
+
 impl __kernel_timespec {
   pub fn from_ms(ms: i64) -> __kernel_timespec {
     __kernel_timespec {
@@ -44,6 +55,13 @@ impl __kernel_timespec {
       tv_nsec: ((ms % 1000) * 1000000),
     }
   }
+
+  pub fn from_duration(duration: Duration) -> __kernel_timespec {
+    __kernel_timespec {
+      tv_sec:  duration.as_secs() as i64,
+      tv_nsec: duration.subsec_nanos() as i64,
+    }
+  }
 }
 
 impl<T: Sized> sqe<T> {
@@ -73,6 +91,28 @@ impl<T: Sized> sqe<T> {
     self
   }
 
+  /* Lets the kernel pick a buffer from group bgid, the sqe's buffer being null with len as the most to transfer */
+  pub fn buffer_select(&mut self, bgid: u16) -> &mut Self {
+    self.flags |= IOSQE_BUFFER_SELECT as u8;
+    self.buf_select = bgid;
+
+    self
+  }
+
+  /* Writes the first word of addr3, whatever the sqe size */
+  pub(crate) fn set_addr3(&mut self, value: u64) -> &mut Self {
+    unsafe { (&mut self.addr3 as *mut T as *mut u64).write_unaligned(value) };
+
+    self
+  }
+
+  /* Treats fd as an index into the registered file table */
+  pub fn fixed_file(&mut self) -> &mut Self {
+    self.flags |= IOSQE_FIXED_FILE as u8;
+
+    self
+  }
+
   /* TODO: Prevent shooting yourself in the foot */
   pub fn direct(&mut self, file_index: u32) -> &mut Self {
     self.file_select = file_index + 1;
@@ -84,12 +124,16 @@ impl<T: Sized> sqe<T> {
   pub fn multishot(&mut self) -> &mut Self {
     self.ioprio |= match self.opcode as u32 {
       IORING_OP_RECVMSG  => IORING_RECV_MULTISHOT,
-      IORING_OP_POLL_ADD => IORING_POLL_ADD_MULTI,
       IORING_OP_ACCEPT   => IORING_ACCEPT_MULTISHOT,
       IORING_OP_RECV     => IORING_RECV_MULTISHOT,
       _                  => 0,
     } as u16;
 
+    /* Poll takes its multishot flag in len rather than ioprio */
+    if self.opcode as u32 == IORING_OP_POLL_ADD {
+      self.len |= IORING_POLL_ADD_MULTI;
+    }
+
     self
   }
 
@@ -120,6 +164,36 @@ impl<T: Sized> cqe<T> {
   pub fn get_data_u64(&self) -> u64 {
     self.user_data
   }
+
+  /* Maps a negative res to its errno */
+  pub fn result(&self) -> Result<u32, Error> {
+    if self.res < 0 { Err(Error::from_raw_os_error(-self.res)) } else { Ok(self.res as u32) }
+  }
+
+  /* Provided buffer the kernel picked for this completion */
+  pub fn buffer_id(&self) -> Option<u16> {
+    if (self.flags & IORING_CQE_F_BUFFER) > 0 { Some((self.flags >> IORING_CQE_BUFFER_SHIFT) as u16) } else { None }
+  }
+
+  /* Multishot request stays armed and will post more completions */
+  pub fn has_more(&self) -> bool {
+    (self.flags & IORING_CQE_F_MORE) > 0
+  }
+
+  /* Incrementally consumed buffer was only partly filled and stays with the kernel */
+  pub fn buf_more(&self) -> bool {
+    (self.flags & IORING_CQE_F_BUF_MORE) > 0
+  }
+
+  /* Socket had more data queued after this recv */
+  pub fn sock_nonempty(&self) -> bool {
+    (self.flags & IORING_CQE_F_SOCK_NOEMPTY) > 0
+  }
+
+  /* Zerocopy notification that the send buffer may be reused */
+  pub fn is_notification(&self) -> bool {
+    (self.flags & IORING_CQE_F_NOTIF) > 0
+  }
 }
 
 impl params {
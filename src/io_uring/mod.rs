@@ -2,7 +2,8 @@
 
 mod definitions;
 mod functions;
-mod constants;
+pub mod constants;
+mod decode;
 
 pub(crate) use {
   definitions::*,
@@ -10,6 +11,9 @@ pub(crate) use {
   constants::*,
 };
 
+pub use decode::{decode_sqe, decode_cqe};
+pub use definitions::open_how;
+
 #[cfg(test)]
 mod size_tests {
   use std::mem::size_of;
@@ -80,8 +84,100 @@ mod size_tests {
     assert_eq!(size_of::<file_index_range>(), 16);
   }
 
+  #[test]
+  fn open_how_size() {
+    assert_eq!(size_of::<open_how>(), 24);
+  }
+
   #[test]
   fn recvmsg_test() {
     assert_eq!(size_of::<recvmsg_out>(), 16);
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod decode_tests {
+  use std::mem::{size_of, zeroed};
+  use libc::{ECONNRESET, MSG_NOSIGNAL};
+  use crate::io_uring::*;
+
+  #[test]
+  fn sqe_display() {
+    let mut sqe: sqe<[u64; 2]> = unsafe { zeroed() };
+
+    sqe.opcode = IORING_OP_SEND as u8;
+    sqe.fd = 7;
+    sqe.addr1 = 0x1000;
+    sqe.len = 512;
+    sqe.op_flags = MSG_NOSIGNAL;
+    sqe.flags = IOSQE_IO_LINK as u8;
+
+    assert_eq!(sqe.to_string(), "SEND fd=7 buf=0x1000 len=512 flags=MSG_NOSIGNAL link");
+    assert_eq!(format!("{:?}", sqe), sqe.to_string());
+
+    let mut sqe: sqe<[u64; 2]> = unsafe { zeroed() };
+
+    sqe.opcode = IORING_OP_OPENAT2 as u8;
+    sqe.fd = -100;
+    sqe.addr1 = 0x2000;
+    sqe.addr2 = 0x3000;
+    sqe.len = 24;
+
+    assert_eq!(sqe.to_string(), "OPENAT2 dirfd=-100 path=0x2000 how=0x3000 size=24");
+  }
+
+  #[test]
+  fn cqe_display() {
+    let cqe = cqe::<[u8; 0]> {
+      user_data: 3,
+      res: -ECONNRESET,
+      flags: IORING_CQE_F_MORE | IORING_CQE_F_BUFFER | (12 << IORING_CQE_BUFFER_SHIFT),
+      big_cqe: [],
+    };
+
+    assert_eq!(cqe.to_string(), "user_data=3 res=-ECONNRESET flags=MORE|BUFFER(12)");
+
+    let cqe = cqe::<[u8; 0]> { user_data: 4, res: 0, flags: IORING_CQE_F_NOTIF | 1 << 9, big_cqe: [] };
+
+    assert_eq!(cqe.to_string(), "user_data=4 res=0 flags=NOTIF|0x200");
+  }
+
+  #[test]
+  fn decode_raw() {
+    let mut raw = [0u8; size_of::<cqe<[u8; 0]>>()];
+
+    raw[0] = 9;
+    raw[8..12].copy_from_slice(&42i32.to_ne_bytes());
+
+    assert_eq!(decode_cqe(&raw).unwrap(), "user_data=9 res=42");
+    assert_eq!(decode_sqe(&[0u8; 64]).unwrap(), "NOP");
+    assert!(decode_sqe(&[0u8; 10]).is_err());
+  }
+}
+
+#[cfg(test)]
+mod cqe_tests {
+  use libc::EAGAIN;
+  use crate::io_uring::*;
+
+  #[test]
+  fn cqe_accessors() {
+    let mut cqe = cqe::<[u8; 0]> { user_data: 0, res: -EAGAIN, flags: 0, big_cqe: [] };
+
+    assert_eq!(cqe.result().unwrap_err().raw_os_error(), Some(EAGAIN));
+    assert_eq!(cqe.buffer_id(), None);
+    assert!(!cqe.has_more() && !cqe.sock_nonempty() && !cqe.is_notification() && !cqe.buf_more());
+
+    cqe.res = 12;
+    cqe.flags = IORING_CQE_F_BUFFER | IORING_CQE_F_MORE | IORING_CQE_F_SOCK_NOEMPTY | (7 << IORING_CQE_BUFFER_SHIFT);
+
+    assert_eq!(cqe.result().unwrap(), 12);
+    assert_eq!(cqe.buffer_id(), Some(7));
+    assert!(cqe.has_more() && cqe.sock_nonempty() && !cqe.is_notification());
+
+    cqe.flags = IORING_CQE_F_BUFFER | IORING_CQE_F_BUF_MORE | (7 << IORING_CQE_BUFFER_SHIFT);
+
+    assert!(cqe.buf_more());
+    assert_eq!(cqe.to_string(), "user_data=0 res=12 flags=BUF_MORE|BUFFER(7)");
+  }
+}
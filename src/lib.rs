@@ -6,5 +6,10 @@ mod squeue;
 mod cqueue;
 mod ring;
 
-pub type RSRing  = ring::Ring<[u64; 2], [u8; 0]>;
-// pub type RSRing = ring::Ring<[u8; 80], [u8; 16]>;
\ No newline at end of file
+pub use io_uring::{decode_sqe, decode_cqe, open_how};
+pub use ring::{Token, ZcSend, CancelMatch, FixedFiles, BufRing};
+pub use cqueue::{Completions, CqeGuard};
+pub use io_uring::constants;
+
+pub type RSRing    = ring::Ring<[u64; 2], [u8; 0]>;
+pub type RSRing128 = ring::Ring<[u8; 80], [u8; 16]>;
\ No newline at end of file
@@ -0,0 +1,209 @@
+use std::io::Error;
+use std::mem::size_of;
+use std::ffi::{c_int, c_void};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicU16, Ordering};
+use libc::{fcntl, EINVAL, F_DUPFD_CLOEXEC};
+
+use crate::io_uring::{self, *};
+use crate::util::Map;
+use crate::ring::Ring;
+
+/* Ring of provided buffers shared with the kernel, which picks one per completion of group bgid,
+   the group being unregistered once dropped through a duplicate of the ring fd that keeps its ring alive */
+#[derive(Debug)]
+pub struct BufRing {
+  ring_fd: Option<OwnedFd>,
+  owner:   usize,
+  ring:    Map<io_uring::buf>,
+  data:    Box<[u8]>,
+  offsets: Box<[usize]>,
+  buf_len: usize,
+  entries: u16,
+  bgid:    u16,
+  tail:    u16,
+  inc:     bool,
+}
+
+impl BufRing {
+  pub fn bgid(&self) -> u16 {
+    self.bgid
+  }
+
+  pub fn entries(&self) -> u16 {
+    self.entries
+  }
+
+  pub fn buf_len(&self) -> usize {
+    self.buf_len
+  }
+
+  /* Whether buffers are consumed incrementally, each staying selected until full */
+  pub fn is_incremental(&self) -> bool {
+    self.inc
+  }
+
+  /* len bytes of buffer bid starting at offset, None if bid is not one of this ring's */
+  pub fn get(&self, bid: u16, offset: usize, len: usize) -> Option<&[u8]> {
+    if bid >= self.entries {
+      return None;
+    }
+
+    let buf = &self.data[bid as usize * self.buf_len..][..self.buf_len];
+    let offset = offset.min(self.buf_len);
+
+    Some(&buf[offset..][..len.min(self.buf_len - offset)])
+  }
+
+  /* Bytes of buffer bid already handed out by incremental completions */
+  pub fn offset(&self, bid: u16) -> Option<usize> {
+    self.offsets.get(bid as usize).copied()
+  }
+
+  /* Buffer id and the bytes cqe filled, which for incremental buffers follow those of earlier completions,
+     None for a buffer id this ring never handed out, as from a completion of another group */
+  pub fn buffer<U: Sized>(&mut self, cqe: &io_uring::cqe<U>) -> Option<(u16, &[u8])> {
+    let bid = cqe.buffer_id().filter(|&bid| bid < self.entries)?;
+    let len = cqe.result().ok()? as usize;
+    let offset = self.offsets[bid as usize];
+
+    if self.inc {
+      self.offsets[bid as usize] = if cqe.buf_more() { offset + len } else { 0 };
+    }
+
+    Some((bid, self.get(bid, offset, len)?))
+  }
+
+  /* Hands buffer bid back to the kernel, never while its last completion had IORING_CQE_F_BUF_MORE, EINVAL if bid is not one of this ring's */
+  pub fn recycle(&mut self, bid: u16) -> Result<(), Error> {
+    if bid >= self.entries {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    self.offsets[bid as usize] = 0;
+    self.push(bid);
+    self.publish();
+
+    Ok(())
+  }
+
+  /* Places bid, already checked against entries, at the local tail without making it visible yet */
+  fn push(&mut self, bid: u16) {
+    let index = (self.tail & (self.entries - 1)) as usize;
+    let addr = unsafe { self.data.as_ptr().add(bid as usize * self.buf_len) } as u64;
+
+    unsafe {
+      let buf = &mut *self.ring.add(index);
+
+      buf.addr = addr;
+      buf.len = self.buf_len as u32;
+      buf.bid = bid;
+    }
+    self.tail = self.tail.wrapping_add(1);
+  }
+
+  /* The shared tail overlays resv of the first entry */
+  fn publish(&mut self) {
+    let tail = unsafe { &*((self.ring.raw() as *mut u8).add(14) as *const AtomicU16) };
+
+    tail.store(self.tail, Ordering::Release);
+  }
+}
+
+impl Drop for BufRing {
+  fn drop(&mut self) {
+    if let Some(fd) = self.ring_fd.take() {
+      let _ = unregister_pbuf_ring(fd.as_raw_fd(), self.bgid);
+    }
+  }
+}
+
+fn unregister_pbuf_ring(ring_fd: c_int, bgid: u16) -> Result<(), Error> {
+  let mut reg = io_uring::buf_reg {
+    ring_addr: 0,
+    ring_entires: 0,
+    bgid: bgid,
+    pad: 0,
+    resv: [0; 3],
+  };
+
+  io_uring::register(ring_fd, IORING_UNREGISTER_PBUF_RING, &mut reg as *mut io_uring::buf_reg as *mut c_void, 1)?;
+
+  Ok(())
+}
+
+impl<T: Sized, U: Sized> Ring<T, U> {
+  /* Registers a kernel allocated ring of entries buffers of buf_len bytes as group bgid, entries being a power of two */
+  pub fn register_buf_ring(&mut self, entries: u16, buf_len: usize, bgid: u16) -> Result<BufRing, Error> {
+    self.setup_buf_ring(entries, buf_len, bgid, 0)
+  }
+
+  /* Registers a buffer ring whose buffers are consumed incrementally, completions reporting IORING_CQE_F_BUF_MORE until one is full */
+  pub fn register_buf_ring_inc(&mut self, entries: u16, buf_len: usize, bgid: u16) -> Result<BufRing, Error> {
+    self.setup_buf_ring(entries, buf_len, bgid, IOU_PBUF_RING_INC)
+  }
+
+  fn setup_buf_ring(&mut self, entries: u16, buf_len: usize, bgid: u16, flags: u32) -> Result<BufRing, Error> {
+    if !entries.is_power_of_two() || entries > 1 << 15 || buf_len == 0 || buf_len > u32::MAX as usize {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let ring_fd = match unsafe { fcntl(self.ring_fd, F_DUPFD_CLOEXEC, 0) } {
+      -1 => return Err(Error::last_os_error()),
+      fd => unsafe { OwnedFd::from_raw_fd(fd) },
+    };
+    let mut reg = io_uring::buf_reg {
+      ring_addr: 0,
+      ring_entires: entries as u32,
+      bgid: bgid,
+      pad: (IOU_PBUF_RING_MMAP | flags) as u16,
+      resv: [0; 3],
+    };
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_PBUF_RING, &mut reg as *mut io_uring::buf_reg as *mut c_void, 1)?;
+
+    let offset = IORING_OFF_PBUF_RING | (bgid as u64) << IORING_OFF_PBUF_SHIFT;
+    let ring = match Map::new(self.ring_fd, entries as usize * size_of::<io_uring::buf>(), offset as i64) {
+      Ok(ring) => ring,
+      Err(err) => {
+        unregister_pbuf_ring(self.ring_fd, bgid)?;
+        return Err(err);
+      }
+    };
+    let mut br = BufRing {
+      ring_fd: Some(ring_fd),
+      owner: self.ring.raw() as usize,
+      ring: ring,
+      data: vec![0u8; entries as usize * buf_len].into_boxed_slice(),
+      offsets: vec![0; entries as usize].into_boxed_slice(),
+      buf_len: buf_len,
+      entries: entries,
+      bgid: bgid,
+      tail: 0,
+      inc: (flags & IOU_PBUF_RING_INC) > 0,
+    };
+
+    for bid in 0..entries {
+      br.push(bid);
+    }
+    br.publish();
+
+    Ok(br)
+  }
+
+  /* Unregisters the group, handing br back if that fails or it belongs to another ring */
+  pub fn unregister_buf_ring(&mut self, mut br: BufRing) -> Result<(), (BufRing, Error)> {
+    let fd = match br.ring_fd.as_ref() {
+      Some(fd) if br.owner == self.ring.raw() as usize => fd.as_raw_fd(),
+      _ => return Err((br, Error::from_raw_os_error(EINVAL))),
+    };
+
+    match unregister_pbuf_ring(fd, br.bgid) {
+      Ok(()) => {
+        br.ring_fd = None;
+        Ok(())
+      },
+      Err(err) => Err((br, err)),
+    }
+  }
+}
@@ -0,0 +1,269 @@
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::io::Error;
+use std::ffi::{c_int, c_void, CString};
+use libc::{sigset_t, EBUSY, EINVAL};
+
+use crate::io_uring::{self, IORING_SEND_ZC_REPORT_USAGE, IORING_NOTIF_USAGE_ZC_COPIED};
+use crate::ring::{Ring, CancelMatch};
+
+/* user_data of dispatched requests is tagged to keep it apart from plain user_data,
+   the generation in bits 32..63 telling a live token from user_data that merely has the tag set */
+const TOKEN_TAG: u64 = 1 << 63;
+const TOKEN_GENERATION: u32 = 0x7FFF_FFFF;
+
+type OnceHandler<U>  = Box<dyn FnOnce(&io_uring::cqe<U>)>;
+type MultiHandler<U> = Box<dyn FnMut(&io_uring::cqe<U>)>;
+
+pub(crate) enum Handler<U: Sized> {
+  Once(OnceHandler<U>),
+  Multi(MultiHandler<U>),
+}
+
+impl<U: Sized> fmt::Debug for Handler<U> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Handler::Once(_)  => write!(f, "Handler::Once"),
+      Handler::Multi(_) => write!(f, "Handler::Multi"),
+    }
+  }
+}
+
+/* Outcome of a zero-copy send, reported once its buffer may be reused */
+#[derive(Debug)]
+pub struct ZcSend {
+  pub result: Result<u32, Error>,
+  /* The kernel fell back to copying, so zero-copy bought nothing */
+  pub copied: bool,
+}
+
+/* Identifies a request submitted through the dispatcher, stored in its user_data */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Token(u64);
+
+impl Token {
+  pub fn user_data(&self) -> u64 {
+    self.0
+  }
+
+  fn new(key: usize, generation: u32) -> Token {
+    Token(TOKEN_TAG | (generation as u64) << 32 | key as u32 as u64)
+  }
+
+  /* Slot and generation of a tagged user_data */
+  fn key(user_data: u64) -> Option<(usize, u32)> {
+    if (user_data & TOKEN_TAG) > 0 {
+      Some((user_data as u32 as usize, (user_data >> 32) as u32 & TOKEN_GENERATION))
+    } else {
+      None
+    }
+  }
+}
+
+/* Completion dispatch keyed by user_data */
+impl<T: Sized, U: Sized> Ring<T, U> {
+  /* Preps an sqe whose completion is handed to handler once */
+  pub fn prep_once<P, F>(&mut self, prep: P, handler: F) -> Option<Token>
+  where
+    P: for<'a> FnOnce(&'a mut Self) -> Option<&'a mut io_uring::sqe<T>>,
+    F: FnOnce(&io_uring::cqe<U>) + 'static,
+  {
+    self.prep_handler(prep, Handler::Once(Box::new(handler)))
+  }
+
+  /* Preps a multishot sqe whose completions are handed to handler until IORING_CQE_F_MORE is clear */
+  pub fn prep_multi<P, F>(&mut self, prep: P, handler: F) -> Option<Token>
+  where
+    P: for<'a> FnOnce(&'a mut Self) -> Option<&'a mut io_uring::sqe<T>>,
+    F: FnMut(&io_uring::cqe<U>) + 'static,
+  {
+    self.prep_handler(prep, Handler::Multi(Box::new(handler)))
+  }
+
+  fn prep_handler<P>(&mut self, prep: P, handler: Handler<U>) -> Option<Token>
+  where
+    P: for<'a> FnOnce(&'a mut Self) -> Option<&'a mut io_uring::sqe<T>>,
+  {
+    /* Generations start at 1, so user_data with only the tag and a slot set never matches */
+    self.generation = self.generation % TOKEN_GENERATION + 1;
+
+    let key = self.handlers.insert((self.generation, handler));
+    let token = Token::new(key, self.generation);
+
+    match prep(self) {
+      Some(sqe) => {
+        sqe.user_data = token.0;
+        Some(token)
+      },
+      None => {
+        self.handlers.remove(key);
+        None
+      },
+    }
+  }
+
+  /* Cancels whatever is in flight before the ring goes away, freeing the handlers, and the buffers they own, whose
+     final completion arrives within a second and leaking the rest since the kernel may still be using their buffers */
+  pub(crate) fn release_handlers(&mut self) {
+    if self.handlers.is_empty() {
+      return;
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+
+    /* An SQPOLL thread may not have picked up the last submissions yet and cancel cannot find those */
+    while self.sq.unconsumed() > 0 && Instant::now() < deadline {
+      let _ = self.ready(self.sq.unconsumed(), 0, ptr::null::<sigset_t>(), 0);
+      thread::yield_now();
+    }
+
+    let _ = self.cancel_sync(CancelMatch::Any, Some(Duration::from_secs(1)));
+
+    while !self.handlers.is_empty() {
+      for cqe in self.cq.completions() {
+        let (key, generation) = match Token::key(cqe.user_data) {
+          Some(key) => key,
+          None => continue,
+        };
+
+        if !cqe.has_more() && matches!(self.handlers.get(key), Some((live, _)) if *live == generation) {
+          self.handlers.remove(key);
+        }
+      }
+
+      let remaining = deadline.saturating_duration_since(Instant::now());
+
+      if self.handlers.is_empty() || remaining.is_zero() || self.wait_deadline(0, 1, remaining).is_err() {
+        break;
+      }
+    }
+
+    mem::forget(mem::take(&mut self.handlers));
+  }
+
+  /* Number of handlers still waiting on completions */
+  pub fn dispatch_pending(&self) -> usize {
+    self.handlers.len()
+  }
+
+  /* Runs the handler of every ready completion, stopping at the first one without a live token so the caller can reap it */
+  pub fn dispatch(&mut self) -> u32 {
+    let mut count = 0;
+
+    while let Some(cqe) = self.cq.next() {
+      let cqe = unsafe { cqe.read() };
+      let (key, generation) = match Token::key(cqe.user_data) {
+        Some(key) => key,
+        None => break,
+      };
+
+      match self.handlers.get_mut(key) {
+        Some((live, Handler::Multi(handler))) if *live == generation => {
+          handler(&cqe);
+
+          if !cqe.has_more() {
+            self.handlers.remove(key);
+          }
+        },
+        Some((live, Handler::Once(_))) if *live == generation => {
+          if let Some((_, Handler::Once(handler))) = self.handlers.remove(key) {
+            handler(&cqe);
+          }
+        },
+        _ => break,
+      };
+
+      self.cq.advance(1);
+      count += 1;
+    }
+
+    count
+  }
+
+  /* Zero-copy sends from registered buffer buf_index, notifying the handler once the kernel releases it,
+     the registered buffer staying the caller's, EINVAL or EBUSY as send_zc_fixed */
+  pub fn send_zc_fixed_notify<F>(&mut self, sockfd: c_int, buf: *const c_void, len: usize, flags: c_int, buf_index: u16, handler: F) -> Result<Token, Error>
+  where
+    F: FnOnce(ZcSend) + 'static,
+  {
+    if !self.fixed_buffer_contains(buf_index, buf as u64, len) {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    match self.prep_zc(|ring| ring.send_zc_fixed(sockfd, buf, len, flags, IORING_SEND_ZC_REPORT_USAGE as u16, buf_index).ok(), handler) {
+      Some(token) => Ok(token),
+      None => Err(Error::from_raw_os_error(EBUSY)),
+    }
+  }
+}
+
+/* Requests whose buffers are owned by their handler, so they live until the completion is dispatched */
+impl<T: Sized, U: Sized> Ring<T, U> {
+  pub fn setxattr_owned<F>(&mut self, path: CString, name: CString, value: Vec<u8>, flags: c_int, handler: F) -> Option<Token>
+  where
+    F: FnOnce(Result<u32, Error>) + 'static,
+  {
+    let (path_ptr, name_ptr, value_ptr, size) = (path.as_ptr(), name.as_ptr(), value.as_ptr(), value.len());
+
+    self.prep_once(|ring| ring.setxattr(path_ptr, name_ptr, value_ptr as *const c_void, size, flags), move |cqe| {
+      drop((path, name, value));
+      handler(cqe.result());
+    })
+  }
+
+  /* Reads up to size bytes of an xattr, handing the value to the handler */
+  pub fn getxattr_owned<F>(&mut self, path: CString, name: CString, size: usize, handler: F) -> Option<Token>
+  where
+    F: FnOnce(Result<Vec<u8>, Error>) + 'static,
+  {
+    let mut value = vec![0u8; size];
+    let (path_ptr, name_ptr, value_ptr) = (path.as_ptr(), name.as_ptr(), value.as_mut_ptr());
+
+    self.prep_once(|ring| ring.getxattr(path_ptr, name_ptr, value_ptr as *mut c_void, size), move |cqe| {
+      drop((path, name));
+      handler(cqe.result().map(|len| {
+        value.truncate(len as usize);
+        value
+      }));
+    })
+  }
+
+  /* Zero-copy sends the buffer, handing it back to the handler once the kernel releases it */
+  pub fn send_zc_owned<F>(&mut self, sockfd: c_int, buf: Vec<u8>, flags: c_int, handler: F) -> Option<Token>
+  where
+    F: FnOnce(ZcSend, Vec<u8>) + 'static,
+  {
+    let (ptr, len) = (buf.as_ptr(), buf.len());
+
+    self.prep_zc(|ring| ring.send_zc(sockfd, ptr as *const c_void, len, flags, IORING_SEND_ZC_REPORT_USAGE as u16), move |send| handler(send, buf))
+  }
+
+  /* Holds the send's result until the notification, which is absent when the send failed before starting,
+     prep must ask for IORING_SEND_ZC_REPORT_USAGE */
+  fn prep_zc<P, F>(&mut self, prep: P, handler: F) -> Option<Token>
+  where
+    P: for<'a> FnOnce(&'a mut Self) -> Option<&'a mut io_uring::sqe<T>>,
+    F: FnOnce(ZcSend) + 'static,
+  {
+    let mut handler = Some(handler);
+    let mut result = None;
+
+    self.prep_multi(prep, move |cqe| {
+      let copied = if cqe.is_notification() {
+        (cqe.res as u32 & IORING_NOTIF_USAGE_ZC_COPIED) > 0
+      } else {
+        result = Some(cqe.result());
+        false
+      };
+
+      if !cqe.has_more() {
+        if let Some(handler) = handler.take() {
+          handler(ZcSend { result: result.take().unwrap_or(Ok(0)), copied: copied });
+        }
+      }
+    })
+  }
+}
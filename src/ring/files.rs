@@ -0,0 +1,74 @@
+use std::ffi::c_int;
+
+use crate::io_uring::*;
+
+/* Tracks which slots of the registered file table hold a file */
+#[derive(Debug, Clone, Default)]
+pub struct FixedFiles {
+  slots: Vec<bool>,
+  range: (u32, u32),
+}
+
+impl FixedFiles {
+  pub fn len(&self) -> u32 {
+    self.slots.len() as u32
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.slots.is_empty()
+  }
+
+  pub fn is_free(&self, index: u32) -> bool {
+    matches!(self.slots.get(index as usize), Some(false))
+  }
+
+  /* Number of slots without a file */
+  pub fn free(&self) -> u32 {
+    self.slots.iter().filter(|used| !**used).count() as u32
+  }
+
+  /* Slots the kernel picks from for IORING_FILE_INDEX_ALLOC, as (offset, len) */
+  pub fn alloc_range(&self) -> (u32, u32) {
+    self.range
+  }
+
+  pub(crate) fn reset(&mut self, nr: u32) {
+    self.slots = vec![false; nr as usize];
+    self.range = (0, 0);
+  }
+
+  pub(crate) fn set_range(&mut self, offset: u32, len: u32) {
+    self.range = (offset, len);
+  }
+
+  /* Mirrors an update of the table, -1 clearing a slot and IORING_REGISTER_FILES_SKIP leaving it be */
+  pub(crate) fn update(&mut self, offset: u32, fds: &[c_int]) {
+    for (slot, &fd) in self.slots.iter_mut().skip(offset as usize).zip(fds) {
+      if fd != IORING_REGISTER_FILES_SKIP {
+        *slot = fd >= 0;
+      }
+    }
+  }
+
+  /* First free slot the kernel will not hand out on its own */
+  pub(crate) fn reserve(&mut self) -> Option<u32> {
+    let (offset, len) = self.range;
+    let index = (0..self.len()).find(|&i| !self.slots[i as usize] && (i < offset || i - offset >= len))?;
+
+    self.slots[index as usize] = true;
+
+    Some(index)
+  }
+
+  pub(crate) fn occupy(&mut self, index: u32) {
+    if let Some(slot) = self.slots.get_mut(index as usize) {
+      *slot = true;
+    }
+  }
+
+  pub(crate) fn release(&mut self, index: u32) {
+    if let Some(slot) = self.slots.get_mut(index as usize) {
+      *slot = false;
+    }
+  }
+}
@@ -1,13 +1,23 @@
 mod ring;
 mod utility;
 mod syscalls;
+mod register;
+mod dispatch;
+mod files;
+mod buf_ring;
 
 pub use {
   ring::Ring,
+  dispatch::{Token, ZcSend},
+  register::CancelMatch,
+  files::FixedFiles,
+  buf_ring::BufRing,
 };
 
 #[cfg(test)]
 mod ring_tests {
+  use std::time::{Duration, Instant};
+  use libc::{ETIME, EBUSY};
   use crate::ring::Ring;
   use crate::io_uring::*;
 
@@ -35,6 +45,100 @@ mod ring_tests {
     Ok(())
   }
 
+  #[test]
+  fn wait_timeout_test() {
+    let mut ring = Ring::<[u64; 2], [u8; 0]>::new(32).unwrap();
+    let start = Instant::now();
+    let err = ring.wait_timeout(Duration::from_millis(20)).unwrap_err();
+
+    assert_eq!(err.raw_os_error(), Some(ETIME));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    ring.nop().unwrap().set_data_u64(5);
+    ring.submit().unwrap();
+
+    assert_eq!(ring.wait_timeout(Duration::from_secs(1)).unwrap().get_data_u64(), 5);
+  }
+
+  #[test]
+  fn wait_timeout_fallback_test() {
+    let mut ring = Ring::<[u64; 2], [u8; 0]>::new(32).unwrap();
+
+    ring.features &= !IORING_FEAT_EXT_ARG;
+
+    let err = ring.submit_and_wait_timeout(1, Duration::from_millis(10)).unwrap_err();
+
+    assert_eq!(err.raw_os_error(), Some(ETIME));
+
+    ring.nop().unwrap().set_data_u64(1);
+    ring.nop().unwrap().set_data_u64(2);
+
+    assert_eq!(ring.submit_and_wait_timeout(2, Duration::from_secs(1)).unwrap(), 2);
+    assert_eq!(ring.wait().unwrap().get_data_u64(), 1);
+    assert_eq!(ring.wait().unwrap().get_data_u64(), 2);
+
+    /* The timeout left armed by the last wait is removed, neither it nor the removal is handed out */
+    let err = ring.wait_timeout(Duration::from_millis(10)).unwrap_err();
+
+    assert_eq!(err.raw_os_error(), Some(ETIME));
+    assert!(ring.completions().next().is_none());
+    assert!(ring.cq.waits.is_empty());
+
+    /* Nothing is armed when enough completions are already waiting */
+    ring.nop().unwrap().set_data_u64(3);
+    ring.submit_and_wait(1).unwrap();
+
+    assert_eq!(ring.submit_and_wait_timeout(1, Duration::from_secs(1)).unwrap(), 1);
+    assert!(ring.cq.waits.is_empty());
+    assert_eq!(ring.wait().unwrap().get_data_u64(), 3);
+  }
+
+  #[test]
+  fn overflow_test() {
+    let mut ring = Ring::<[u64; 2], [u8; 0]>::new(4).unwrap();
+    let entries = ring.cq.ring_entries;
+
+    for i in 1..=2 {
+      for _ in 0..entries / 2 {
+        ring.nop().unwrap();
+      }
+      ring.submit_and_wait(i * entries / 2).unwrap();
+    }
+    ring.nop().unwrap();
+    ring.submit().unwrap();
+
+    while !ring.backlogged() {
+      std::thread::yield_now();
+    }
+
+    assert_eq!(ring.completions().count() as u32, entries);
+    assert!(ring.wait().is_ok());
+    assert!(!ring.backlogged());
+    assert_eq!(ring.overflow(), 0);
+  }
+
+  #[test]
+  fn overflow_nodrop_test() {
+    let mut ring = Ring::<[u64; 2], [u8; 0]>::new(4).unwrap();
+    let entries = ring.cq.ring_entries;
+
+    ring.features &= !IORING_FEAT_NODROP;
+
+    for i in 1..=2 {
+      for _ in 0..entries / 2 {
+        ring.nop().unwrap();
+      }
+      ring.submit_and_wait(i * entries / 2).unwrap();
+    }
+
+    assert_eq!(ring.submit().unwrap_err().raw_os_error(), Some(EBUSY));
+    assert_eq!(ring.completions().count() as u32, entries);
+
+    ring.nop().unwrap();
+
+    assert!(ring.submit().is_ok());
+  }
+
   // #[test]
   // fn depth_size_test() {
   //   let ring = Ring::<[u64; 2], [u8; 0]>::new(32).unwrap();
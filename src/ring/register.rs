@@ -0,0 +1,270 @@
+use std::io::{Error, IoSliceMut};
+use std::mem::size_of;
+use std::ffi::{c_int, c_void};
+use std::time::Duration;
+use libc::{iovec, EINVAL};
+
+use crate::io_uring::{self, *};
+use crate::ring::{Ring, FixedFiles};
+
+/* Selects the requests cancel_sync waits on */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelMatch {
+  UserData(u64),
+  Fd(c_int),
+  FixedFd(u32),
+  Any,
+}
+
+/* io_uring register ops */
+impl<T: Sized, U: Sized> Ring<T, U> {
+  /* Signals the eventfd whenever completions are posted */
+  pub fn register_eventfd(&mut self, fd: c_int) -> Result<(), Error> {
+    let mut fd = fd;
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_EVENTFD, &mut fd as *mut c_int as *mut c_void, 1)?;
+
+    Ok(())
+  }
+
+  /* Signals the eventfd only for requests that completed asynchronously */
+  pub fn register_eventfd_async(&mut self, fd: c_int) -> Result<(), Error> {
+    let mut fd = fd;
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_EVENTFD_ASYNC, &mut fd as *mut c_int as *mut c_void, 1)?;
+
+    Ok(())
+  }
+
+  pub fn unregister_eventfd(&mut self) -> Result<(), Error> {
+    io_uring::register(self.ring_fd, IORING_UNREGISTER_EVENTFD, std::ptr::null_mut::<c_void>(), 0)?;
+
+    Ok(())
+  }
+
+  /* Toggles eventfd notifications through the cq ring flags without unregistering */
+  pub fn set_eventfd_enabled(&mut self, enabled: bool) {
+    self.cq.set_eventfd_disabled(!enabled);
+  }
+
+  pub fn eventfd_enabled(&self) -> bool {
+    !self.cq.eventfd_disabled()
+  }
+
+  /* Registers buffers for read_fixed / write_fixed, indexed by their position in bufs */
+  pub fn register_buffers(&mut self, bufs: &[IoSliceMut]) -> Result<(), Error> {
+    let nr = bufs.len() as u32;
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_BUFFERS, bufs.as_ptr() as *mut c_void, nr)?;
+
+    self.buffers = bufs.iter().map(|buf| (buf.as_ptr() as u64, buf.len())).collect();
+
+    Ok(())
+  }
+
+  /* Registers an empty table of nr buffers to be filled by update_buffers */
+  pub fn register_buffers_sparse(&mut self, nr: u32) -> Result<(), Error> {
+    let mut reg = io_uring::rsrc_register {
+      rn: nr,
+      flags: IORING_RSRC_REGISTER_SPARSE,
+      resv: 0,
+      data: 0,
+      tags: 0,
+    };
+    let size = size_of::<io_uring::rsrc_register>() as u32;
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_BUFFERS2, &mut reg as *mut io_uring::rsrc_register as *mut c_void, size)?;
+
+    self.buffers = vec![(0, 0); nr as usize];
+
+    Ok(())
+  }
+
+  /* Replaces registered buffers starting at offset, returning how many were replaced, use clear_buffer to empty a slot */
+  pub fn update_buffers(&mut self, offset: u32, bufs: &[IoSliceMut]) -> Result<u32, Error> {
+    if offset as usize + bufs.len() > self.buffers.len() {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let mut up = io_uring::rsrc_update2 {
+      offset: offset,
+      resv1: 0,
+      data: bufs.as_ptr() as u64,
+      tags: 0,
+      nr: bufs.len() as u32,
+      resv2: 0,
+    };
+    let size = size_of::<io_uring::rsrc_update2>() as u32;
+    let done = io_uring::register(self.ring_fd, IORING_REGISTER_BUFFERS_UPDATE, &mut up as *mut io_uring::rsrc_update2 as *mut c_void, size)?;
+
+    for (i, buf) in bufs.iter().take(done as usize).enumerate() {
+      self.buffers[offset as usize + i] = (buf.as_ptr() as u64, buf.len());
+    }
+
+    Ok(done as u32)
+  }
+
+  /* Empties registered buffer index, the kernel only taking a null base as a cleared slot */
+  pub fn clear_buffer(&mut self, index: u32) -> Result<(), Error> {
+    if index as usize >= self.buffers.len() {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let iov = iovec { iov_base: std::ptr::null_mut::<c_void>(), iov_len: 0 };
+    let mut up = io_uring::rsrc_update2 {
+      offset: index,
+      resv1: 0,
+      data: &iov as *const iovec as u64,
+      tags: 0,
+      nr: 1,
+      resv2: 0,
+    };
+    let size = size_of::<io_uring::rsrc_update2>() as u32;
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_BUFFERS_UPDATE, &mut up as *mut io_uring::rsrc_update2 as *mut c_void, size)?;
+
+    self.buffers[index as usize] = (0, 0);
+
+    Ok(())
+  }
+
+  pub fn unregister_buffers(&mut self) -> Result<(), Error> {
+    io_uring::register(self.ring_fd, IORING_UNREGISTER_BUFFERS, std::ptr::null_mut::<c_void>(), 0)?;
+
+    self.buffers.clear();
+
+    Ok(())
+  }
+
+  /* Whether [addr, addr + len) lies inside registered buffer index */
+  pub(crate) fn fixed_buffer_contains(&self, index: u16, addr: u64, len: usize) -> bool {
+    match self.buffers.get(index as usize) {
+      Some(&(base, size)) if size > 0 => addr >= base && matches!(addr.checked_add(len as u64), Some(end) if end <= base + size as u64),
+      _ => false,
+    }
+  }
+
+  /* Registers fds as the fixed file table, -1 leaving a slot empty */
+  pub fn register_files(&mut self, fds: &[c_int]) -> Result<(), Error> {
+    io_uring::register(self.ring_fd, IORING_REGISTER_FILES, fds.as_ptr() as *mut c_void, fds.len() as u32)?;
+
+    self.files.reset(fds.len() as u32);
+    self.files.update(0, fds);
+
+    Ok(())
+  }
+
+  /* Registers an empty table of nr files to be filled by updates or direct opens */
+  pub fn register_files_sparse(&mut self, nr: u32) -> Result<(), Error> {
+    let mut reg = io_uring::rsrc_register {
+      rn: nr,
+      flags: IORING_RSRC_REGISTER_SPARSE,
+      resv: 0,
+      data: 0,
+      tags: 0,
+    };
+    let size = size_of::<io_uring::rsrc_register>() as u32;
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_FILES2, &mut reg as *mut io_uring::rsrc_register as *mut c_void, size)?;
+
+    self.files.reset(nr);
+
+    Ok(())
+  }
+
+  /* Replaces registered files starting at offset, -1 clearing a slot and IORING_REGISTER_FILES_SKIP leaving it be */
+  pub fn update_files(&mut self, offset: u32, fds: &[c_int]) -> Result<u32, Error> {
+    if offset as usize + fds.len() > self.files.len() as usize {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let mut up = io_uring::rsrc_update2 {
+      offset: offset,
+      resv1: 0,
+      data: fds.as_ptr() as u64,
+      tags: 0,
+      nr: fds.len() as u32,
+      resv2: 0,
+    };
+    let size = size_of::<io_uring::rsrc_update2>() as u32;
+    let done = io_uring::register(self.ring_fd, IORING_REGISTER_FILES_UPDATE2, &mut up as *mut io_uring::rsrc_update2 as *mut c_void, size)?;
+
+    self.files.update(offset, &fds[..done as usize]);
+
+    Ok(done as u32)
+  }
+
+  /* Records a files_update from its cqe, res slots having been updated, with IORING_FILE_INDEX_ALLOC fds holding the allocated slots */
+  pub fn files_updated(&mut self, offset: u32, fds: &[c_int], res: i32) {
+    let done = &fds[..(res.max(0) as usize).min(fds.len())];
+
+    if offset != IORING_FILE_INDEX_ALLOC {
+      self.files.update(offset, done);
+      return;
+    }
+    for &index in done {
+      self.files.occupy(index as u32);
+    }
+  }
+
+  /* Restricts IORING_FILE_INDEX_ALLOC to len slots from offset */
+  pub fn set_file_alloc_range(&mut self, offset: u32, len: u32) -> Result<(), Error> {
+    let mut range = io_uring::file_index_range {
+      off: offset,
+      len: len,
+      resv: 0,
+    };
+
+    io_uring::register(self.ring_fd, IORING_REGISTER_FILE_ALLOC_RANGE, &mut range as *mut io_uring::file_index_range as *mut c_void, 0)?;
+
+    self.files.set_range(offset, len);
+
+    Ok(())
+  }
+
+  pub fn unregister_files(&mut self) -> Result<(), Error> {
+    io_uring::register(self.ring_fd, IORING_UNREGISTER_FILES, std::ptr::null_mut::<c_void>(), 0)?;
+
+    self.files.reset(0);
+
+    Ok(())
+  }
+
+  /* Occupancy of the registered file table as far as this ring has seen */
+  pub fn files(&self) -> &FixedFiles {
+    &self.files
+  }
+
+  /* Claims a free slot outside the allocation range, for direct opens or files_update */
+  pub fn reserve_file_slot(&mut self) -> Option<u32> {
+    self.files.reserve()
+  }
+
+  /* Marks a slot free again once its file is closed or cleared */
+  pub fn release_file_slot(&mut self, index: u32) {
+    self.files.release(index);
+  }
+
+  /* Cancels every matching request and blocks until they are gone, failing with ETIME once the timeout elapses */
+  pub fn cancel_sync(&mut self, matcher: CancelMatch, timeout: Option<Duration>) -> Result<u32, Error> {
+    let (addr, fd, flags) = match matcher {
+      CancelMatch::UserData(user_data) => (user_data, -1, 0),
+      CancelMatch::Fd(fd)              => (0, fd, IORING_ASYNC_CANCEL_FD),
+      CancelMatch::FixedFd(index)      => (0, index as c_int, IORING_ASYNC_CANCEL_FD | IORING_ASYNC_CANCEL_FIXED),
+      CancelMatch::Any                 => (0, -1, IORING_ASYNC_CANCEL_ANY),
+    };
+    let mut reg = io_uring::sync_cancel_reg {
+      addr: addr,
+      fd: fd,
+      flags: flags | IORING_ASYNC_CANCEL_ALL,
+      timeout: match timeout {
+        Some(timeout) => __kernel_timespec::from_duration(timeout),
+        None => __kernel_timespec { tv_sec: -1, tv_nsec: -1 },
+      },
+      pad: [0; 4],
+    };
+    let ptr = &mut reg as *mut io_uring::sync_cancel_reg as *mut c_void;
+
+    Ok(io_uring::register(self.ring_fd, IORING_REGISTER_SYNC_CANCEL, ptr, 1)? as u32)
+  }
+}
\ No newline at end of file
@@ -1,10 +1,15 @@
 use std::io::Error;
 use std::mem::size_of;
+use std::ptr;
 use std::ffi::c_void;
+use std::os::fd::{AsRawFd, RawFd};
+use std::time::Duration;
 use libc::{close, EINVAL};
 
 use crate::io_uring::{self, *};
-use crate::util::Map;
+use crate::util::{Map, Slab};
+use crate::ring::dispatch::Handler;
+use crate::ring::FixedFiles;
 use crate::squeue::SQueue;
 use crate::cqueue::CQueue;
 
@@ -17,6 +22,11 @@ pub struct Ring<T: Sized, U: Sized> {
   pub(crate) features:   u32,
   pub(crate) sq:         SQueue<T>,
   pub(crate) cq:         CQueue<U>,
+  pub(crate) timespecs:  Box<[__kernel_timespec]>,
+  pub(crate) handlers:   Slab<(u32, Handler<U>)>,
+  pub(crate) generation: u32,
+  pub(crate) buffers:    Vec<(u64, usize)>,
+  pub(crate) files:      FixedFiles,
 }
 
 impl<T: Sized, U: Sized> Ring<T, U> {
@@ -27,13 +37,15 @@ impl<T: Sized, U: Sized> Ring<T, U> {
       Err(e) => return Err(e)
     };
     let sq_size = p.sq_off.array as usize + p.sq_entries as usize * size_of::<u32>();
-    let cq_size = p.cq_off.cqes as usize + p.cq_entries as usize * size_of::<io_uring::cqe<T>>();
+    let cq_size = p.cq_off.cqes as usize + p.cq_entries as usize * size_of::<io_uring::cqe<U>>();
     let size = core::cmp::max(sq_size, cq_size);
     let ring = Map::new(fd, size, IORING_OFF_SQ_RING as i64)?;
-    let sqes = Map::new(fd, size, IORING_OFF_SQES as i64)?;
+    let sqes = Map::new(fd, p.sq_entries as usize * size_of::<io_uring::sqe<T>>(), IORING_OFF_SQES as i64)?;
     let sq = unsafe { SQueue::<T>::new(ring.raw(), &p, sqes) };
     let cq = unsafe { CQueue::<U>::new(ring.raw(), &p) };
 
+    let timespecs = vec![__kernel_timespec::from_ms(0); sq.ring_entries as usize].into_boxed_slice();
+
     for i in 0..sq.ring_entries {
       unsafe {
         *sq.array.add(i as usize) = i;
@@ -48,9 +60,25 @@ impl<T: Sized, U: Sized> Ring<T, U> {
       features: p.features,
       sq: sq,
       cq: cq,
+      timespecs: timespecs,
+      handlers: Slab::new(),
+      generation: 0,
+      buffers: Vec::new(),
+      files: FixedFiles::default(),
     })
   }
 
+  /* Preps an sqe whose addr points at a timespec held in the ring, one per sq slot so it outlives submission */
+  pub(crate) fn prep_timespec(&mut self, op: u32, fd: i32, ts: Duration, len: u32, offset: u64, flags: i32) -> Option<&mut io_uring::sqe<T>> {
+    let slot = self.sq.slot();
+    let sqe = self.sq.prep(op, fd, ptr::null::<c_void>(), len, offset, flags)?;
+
+    self.timespecs[slot] = __kernel_timespec::from_duration(ts);
+    sqe.addr1 = &self.timespecs[slot] as *const __kernel_timespec as u64;
+
+    Some(sqe)
+  }
+
   pub(crate) fn init_flags() -> Result<u32, Error> {
     let sqe_setup = match size_of::<sqe<T>>() {
       64  => 0,
@@ -72,8 +100,17 @@ impl<T: Sized, U: Sized> Ring<T, U> {
   }
 }
 
+/* The ring fd, which msg_ring targets */
+impl<T: Sized, U: Sized> AsRawFd for Ring<T, U> {
+  fn as_raw_fd(&self) -> RawFd {
+    self.ring_fd
+  }
+}
+
 impl<T: Sized, U: Sized> Drop for Ring<T, U> {
   fn drop(&mut self) {
+    self.release_handlers();
+
     unsafe { 
       close(self.ring_fd);
       close(self.enter_fd);
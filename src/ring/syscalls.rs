@@ -1,4 +1,7 @@
 use std::ptr;
+use std::io::Error;
+use std::mem::size_of;
+use std::time::Duration;
 use std::ffi::{c_int, c_uint, c_char, c_void};
 use libc::*;
 
@@ -21,7 +24,7 @@ impl<T: Sized, U: Sized> Ring<T, U> {
 
   #[inline]
   pub fn readv(&mut self, fd: c_int, iov: *const iovec, iovcnt: c_int) -> Option<&mut io_uring::sqe<T>> {
-    return self.preadv(fd, iov, iovcnt, 0);
+    return self.preadv(fd, iov, iovcnt, self.cur_pos());
   }
 
   #[inline]
@@ -36,7 +39,7 @@ impl<T: Sized, U: Sized> Ring<T, U> {
 
   #[inline]
   pub fn writev(&mut self, fd: c_int, iov: *const iovec, iovcnt: c_int) -> Option<&mut io_uring::sqe<T>> {
-    return self.pwritev(fd, iov, iovcnt, 0);
+    return self.pwritev(fd, iov, iovcnt, self.cur_pos());
   }
 
   #[inline]
@@ -109,14 +112,32 @@ impl<T: Sized, U: Sized> Ring<T, U> {
     return self.sq.prep(IORING_OP_STATX, dirfd, pathname as *const c_void, mask as u32, statxbuf as u64, flags);
   }
 
+  #[inline]
+	pub fn pread(&mut self, fd: c_int, buf: *mut c_void, count: size_t, offset: off_t) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_READ, fd, buf, count as u32, offset as u64, 0);
+  }
+
+  #[inline]
+	pub fn pwrite(&mut self, fd: c_int, buf: *const c_void, count: size_t, offset: off_t) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_WRITE, fd, buf, count as u32, offset as u64, 0);
+  }
+
+  /* Reads at and advances the file position */
   #[inline]
 	pub fn read(&mut self, fd: c_int, buf: *mut c_void, count: size_t) -> Option<&mut io_uring::sqe<T>> {
-    return self.sq.prep(IORING_OP_READ, fd, buf, count as u32, 0, 0);
+    return self.pread(fd, buf, count, self.cur_pos());
   }
 
+  /* Writes at and advances the file position */
   #[inline]
 	pub fn write(&mut self, fd: c_int, buf: *const c_void, count: size_t) -> Option<&mut io_uring::sqe<T>> {
-    return self.sq.prep(IORING_OP_WRITE, fd, buf, count as u32, 0, 0);
+    return self.pwrite(fd, buf, count, self.cur_pos());
+  }
+
+  /* Offset -1 uses the file position where supported, older kernels only know explicit offsets */
+  #[inline]
+  fn cur_pos(&self) -> off_t {
+    if (self.features & IORING_FEAT_RW_CUR_POS) > 0 { -1 } else { 0 }
   }
 
   #[inline]
@@ -139,9 +160,10 @@ impl<T: Sized, U: Sized> Ring<T, U> {
     return self.sq.prep(IORING_OP_RECV, sockfd, buf, len as u32, 0, flags);
   }
 
+  /* Opens pathname relative to dirfd as described by how, whose RESOLVE_* flags can keep the walk inside dirfd */
   #[inline]
-	pub fn openat2(&mut self, dirfd: c_int, pathname: *const c_char, flags: c_int, mode: mode_t) -> Option<&mut io_uring::sqe<T>> {
-    return self.sq.prep(IORING_OP_OPENAT, dirfd, pathname as *const c_void, mode, 0, flags);
+	pub fn openat2(&mut self, dirfd: c_int, pathname: *const c_char, how: *const io_uring::open_how) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_OPENAT2, dirfd, pathname as *const c_void, size_of::<io_uring::open_how>() as u32, how as u64, 0);
   }
 
   #[inline]
@@ -227,20 +249,29 @@ impl<T: Sized, U: Sized> Ring<T, U> {
     return self.sq.prep(IORING_OP_FSETXATTR, fd, name as *const c_void, size as u32, value as u64, flags);
   }
 
-  // #[inline]
-	// pub fn setxattr(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
+  #[inline]
+	pub fn setxattr(&mut self, path: *const c_char, name: *const c_char, value: *const c_void, size: size_t, flags: c_int) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.sq.prep(IORING_OP_SETXATTR, 0, name as *const c_void, size as u32, value as u64, flags)?;
+
+    sqe.set_addr3(path as u64);
+
+    Some(sqe)
+  }
 
   #[inline]
 	pub fn fgetxattr(&mut self, fd: c_int, name: *const c_char, value: *mut c_void, size: size_t) -> Option<&mut io_uring::sqe<T>> {
     return self.sq.prep(IORING_OP_FGETXATTR, fd, name as *const c_void, size as u32, value as u64, 0);
   }
 
-  // #[inline]
-	// pub fn getxattr(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
+  /* Reads an xattr by path, res being its length, which a size of 0 asks for without copying */
+  #[inline]
+	pub fn getxattr(&mut self, path: *const c_char, name: *const c_char, value: *mut c_void, size: size_t) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.sq.prep(IORING_OP_GETXATTR, 0, name as *const c_void, size as u32, value as u64, 0)?;
+
+    sqe.set_addr3(path as u64);
+
+    Some(sqe)
+  }
 
   #[inline]
 	pub fn socket(&mut self, domain: c_int, sock_type: c_int, protocol: c_int) -> Option<&mut io_uring::sqe<T>> {
@@ -255,68 +286,259 @@ impl<T: Sized, U: Sized> Ring<T, U> {
     return self.sq.prep(IORING_OP_NOP, -1, NULL, 0, 0, 0);
   }
 
-  // #[inline]
-	// pub fn read_fixed(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn write_fixed(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn poll_add(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn poll_remove(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn timeout(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn timeout_remove(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn async_cancel(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn link_timeout(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn files_update(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn provide_buffers(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn remove_buffers(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn msg_ring(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
-
-  // #[inline]
-	// pub fn uring_cmd(&mut self, fd: c_int) -> Option<&mut io_uring::sqe<T>> {
-  //   return self.sq.prep();
-  // }
+  /* EINVAL if buf does not lie inside registered buffer buf_index, EBUSY if the sq is full */
+  #[inline]
+	pub fn read_fixed(&mut self, fd: c_int, buf: *mut c_void, nbytes: size_t, offset: off_t, buf_index: u16) -> Result<&mut io_uring::sqe<T>, Error> {
+    if !self.fixed_buffer_contains(buf_index, buf as u64, nbytes) {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let sqe = match self.sq.prep(IORING_OP_READ_FIXED, fd, buf, nbytes as u32, offset as u64, 0) {
+      Some(sqe) => sqe,
+      None => return Err(Error::from_raw_os_error(EBUSY)),
+    };
+
+    sqe.buf_select = buf_index;
+
+    Ok(sqe)
+  }
+
+  /* EINVAL if buf does not lie inside registered buffer buf_index, EBUSY if the sq is full */
+  #[inline]
+	pub fn write_fixed(&mut self, fd: c_int, buf: *const c_void, nbytes: size_t, offset: off_t, buf_index: u16) -> Result<&mut io_uring::sqe<T>, Error> {
+    if !self.fixed_buffer_contains(buf_index, buf as u64, nbytes) {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let sqe = match self.sq.prep(IORING_OP_WRITE_FIXED, fd, buf, nbytes as u32, offset as u64, 0) {
+      Some(sqe) => sqe,
+      None => return Err(Error::from_raw_os_error(EBUSY)),
+    };
+
+    sqe.buf_select = buf_index;
+
+    Ok(sqe)
+  }
+
+  /* Sends without copying, the buffer staying in use until a second cqe with IORING_CQE_F_NOTIF,
+     zc_flags being IORING_RECVSEND_* and IORING_SEND_ZC_* flags */
+  #[inline]
+	pub fn send_zc(&mut self, sockfd: c_int, buf: *const c_void, len: size_t, flags: c_int, zc_flags: u16) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.sq.prep(IORING_OP_SEND_ZC, sockfd, buf, len as u32, 0, flags)?;
+
+    sqe.ioprio = zc_flags;
+
+    Some(sqe)
+  }
+
+  /* EINVAL if buf does not lie inside registered buffer buf_index, EBUSY if the sq is full */
+  #[inline]
+	pub fn send_zc_fixed(&mut self, sockfd: c_int, buf: *const c_void, len: size_t, flags: c_int, zc_flags: u16, buf_index: u16) -> Result<&mut io_uring::sqe<T>, Error> {
+    if !self.fixed_buffer_contains(buf_index, buf as u64, len) {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let sqe = match self.send_zc(sockfd, buf, len, flags, zc_flags | IORING_RECVSEND_FIXED_BUF as u16) {
+      Some(sqe) => sqe,
+      None => return Err(Error::from_raw_os_error(EBUSY)),
+    };
+
+    sqe.buf_select = buf_index;
+
+    Ok(sqe)
+  }
+
+  #[inline]
+	pub fn poll_add(&mut self, fd: c_int, poll_mask: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_POLL_ADD, fd, NULL, 0, 0, poll_mask as i32);
+  }
+
+  #[inline]
+	pub fn poll_multishot(&mut self, fd: c_int, poll_mask: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_POLL_ADD, fd, NULL, IORING_POLL_ADD_MULTI, 0, poll_mask as i32);
+  }
+
+  /* Removes the poll armed with user_data */
+  #[inline]
+	pub fn poll_remove(&mut self, user_data: u64) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_POLL_REMOVE, -1, user_data as *const c_void, 0, 0, 0);
+  }
+
+  /* Updates the poll armed with old_user_data in place, flags being IORING_POLL_UPDATE_* and IORING_POLL_ADD_MULTI */
+  #[inline]
+	pub fn poll_update(&mut self, old_user_data: u64, new_user_data: u64, poll_mask: c_uint, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_POLL_REMOVE, -1, old_user_data as *const c_void, flags, new_user_data, poll_mask as i32);
+  }
+
+  /* Fires after ts or once count other requests complete, ts being absolute against the flag's clock with IORING_TIMEOUT_ABS */
+  #[inline]
+	pub fn timeout(&mut self, ts: Duration, count: c_uint, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.prep_timespec(IORING_OP_TIMEOUT, -1, ts, 1, count as u64, flags as i32);
+  }
+
+  /* Re-arms the timeout submitted with user_data to fire after ts instead */
+  #[inline]
+	pub fn timeout_update(&mut self, user_data: u64, ts: Duration, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.prep_timespec(IORING_OP_TIMEOUT_REMOVE, -1, ts, 0, 0, (flags | IORING_TIMEOUT_UPDATE) as i32)?;
+
+    sqe.addr2 = sqe.addr1;
+    sqe.addr1 = user_data;
+
+    Some(sqe)
+  }
+
+  #[inline]
+	pub fn timeout_remove(&mut self, user_data: u64, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_TIMEOUT_REMOVE, -1, user_data as *const c_void, 0, 0, flags as i32);
+  }
+
+  /* Cancels the request submitted with user_data, or every match with IORING_ASYNC_CANCEL_ALL, res being the number cancelled */
+  #[inline]
+	pub fn async_cancel(&mut self, user_data: u64, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_ASYNC_CANCEL, -1, user_data as *const c_void, 0, 0, flags as i32);
+  }
+
+  /* Cancels requests on fd, a fixed file index with IORING_ASYNC_CANCEL_FIXED */
+  #[inline]
+	pub fn async_cancel_fd(&mut self, fd: c_int, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_ASYNC_CANCEL, fd, NULL, 0, 0, (flags | IORING_ASYNC_CANCEL_FD) as i32);
+  }
+
+  /* Cancels every in-flight request */
+  #[inline]
+	pub fn async_cancel_any(&mut self) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_ASYNC_CANCEL, -1, NULL, 0, 0, (IORING_ASYNC_CANCEL_ANY | IORING_ASYNC_CANCEL_ALL) as i32);
+  }
+
+  /* Cancels the preceding sqe, which must carry IOSQE_IO_LINK, unless it completes within ts */
+  #[inline]
+	pub fn link_timeout(&mut self, ts: Duration, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.prep_timespec(IORING_OP_LINK_TIMEOUT, -1, ts, 1, 0, flags as i32);
+  }
+
+  /* Preps a request bounded by a link timeout, a request cut short completing with -ETIME rather than -ECANCELED
+     The user_data must be set within prep, it is returned once both sqes are queued */
+	pub fn with_deadline<P>(&mut self, timeout: Duration, prep: P) -> Option<u64>
+  where
+    P: for<'a> FnOnce(&'a mut Self) -> Option<&'a mut io_uring::sqe<T>>,
+  {
+    if self.sq.ring_entries - self.sq.remaining() < 2 {
+      return None;
+    }
+
+    let sqe = prep(self)?;
+    let user_data = sqe.link().user_data;
+    let deadline = self.cq.add_deadline(user_data);
+
+    self.link_timeout(timeout, 0)?.set_data_u64(deadline);
+
+    Some(user_data)
+  }
+
+  /* Updates registered file slots from offset, or with IORING_FILE_INDEX_ALLOC writes the allocated slots back into fds,
+     files() only reflects the update once its result is passed to files_updated */
+  #[inline]
+	pub fn files_update(&mut self, fds: &mut [c_int], offset: u32) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_FILES_UPDATE, -1, fds.as_ptr() as *const c_void, fds.len() as u32, offset as u64, 0);
+  }
+
+  /* Hands nr buffers of len bytes laid out from addr to group bgid, numbered from bid */
+  #[inline]
+	pub fn provide_buffers(&mut self, addr: *mut c_void, len: c_int, nr: c_int, bgid: u16, bid: u16) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.sq.prep(IORING_OP_PROVIDE_BUFFERS, nr, addr, len as u32, bid as u64, 0)?;
+
+    sqe.buf_select = bgid;
+
+    Some(sqe)
+  }
+
+  /* Takes back up to nr unused buffers from group bgid, res being the number removed */
+  #[inline]
+	pub fn remove_buffers(&mut self, nr: c_int, bgid: u16) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.sq.prep(IORING_OP_REMOVE_BUFFERS, nr, NULL, 0, 0, 0)?;
+
+    sqe.buf_select = bgid;
+
+    Some(sqe)
+  }
+
+  /* Posts a cqe carrying res and user_data into the ring behind fd */
+  #[inline]
+	pub fn msg_ring(&mut self, fd: c_int, res: u32, user_data: u64, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    return self.sq.prep(IORING_OP_MSG_RING, fd, IORING_MSG_DATA as *const c_void, res, user_data, flags as i32);
+  }
+
+  /* Like msg_ring, the posted cqe also carrying cqe_flags */
+  #[inline]
+	pub fn msg_ring_cqe_flags(&mut self, fd: c_int, res: u32, user_data: u64, flags: c_uint, cqe_flags: u32) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.sq.prep(IORING_OP_MSG_RING, fd, IORING_MSG_DATA as *const c_void, res, user_data, (flags | IORING_MSG_RING_FLAGS_PASS) as i32)?;
+
+    sqe.file_select = cqe_flags;
+
+    Some(sqe)
+  }
+
+  /* Installs fixed file source_index into slot target_index of the ring behind fd, or any free one with IORING_FILE_INDEX_ALLOC,
+     both rings seeing res as the allocated slot and the target getting user_data unless IORING_MSG_RING_CQE_SKIP is set */
+  #[inline]
+	pub fn msg_ring_fd(&mut self, fd: c_int, source_index: u32, target_index: u32, user_data: u64, flags: c_uint) -> Option<&mut io_uring::sqe<T>> {
+    let sqe = self.sq.prep(IORING_OP_MSG_RING, fd, IORING_MSG_SEND_FD as *const c_void, 0, user_data, flags as i32)?;
+
+    sqe.set_addr3(source_index as u64);
+    sqe.file_select = if target_index == IORING_FILE_INDEX_ALLOC { target_index } else { target_index + 1 };
+
+    Some(sqe)
+  }
+
+  /* Bytes queued for reading on a socket */
+  #[inline]
+	pub fn siocinq(&mut self, sockfd: c_int) -> Option<&mut io_uring::sqe<T>> {
+    return self.cmd_sock(SOCKET_URING_OP_SIOCINQ, sockfd, 0, 0, NULL, 0);
+  }
+
+  /* Bytes written to a socket but not yet acknowledged */
+  #[inline]
+	pub fn siocoutq(&mut self, sockfd: c_int) -> Option<&mut io_uring::sqe<T>> {
+    return self.cmd_sock(SOCKET_URING_OP_SIOCOUTQ, sockfd, 0, 0, NULL, 0);
+  }
+
+  /* Reads a SOL_SOCKET option into optval, res being the option length */
+  #[inline]
+	pub fn getsockopt(&mut self, sockfd: c_int, level: c_int, optname: c_int, optval: *mut c_void, optlen: socklen_t) -> Option<&mut io_uring::sqe<T>> {
+    return self.cmd_sock(SOCKET_URING_OP_GETSOCKOPT, sockfd, level, optname, optval, optlen);
+  }
+
+  #[inline]
+	pub fn setsockopt(&mut self, sockfd: c_int, level: c_int, optname: c_int, optval: *const c_void, optlen: socklen_t) -> Option<&mut io_uring::sqe<T>> {
+    return self.cmd_sock(SOCKET_URING_OP_SETSOCKOPT, sockfd, level, optname, optval, optlen);
+  }
+
+  /* Socket commands keep cmd_op in off, level and optname in addr, optlen in file_index and optval in addr3 */
+  fn cmd_sock(&mut self, cmd_op: u32, sockfd: c_int, level: c_int, optname: c_int, optval: *const c_void, optlen: socklen_t) -> Option<&mut io_uring::sqe<T>> {
+    let addr = (level as u32 as u64 | (optname as u32 as u64) << 32) as *const c_void;
+    let sqe = self.sq.prep(IORING_OP_URING_CMD, sockfd, addr, 0, cmd_op as u64, 0)?;
+
+    sqe.file_select = optlen;
+    sqe.set_addr3(optval as u64);
+
+    Some(sqe)
+  }
+
+  /* Passes cmd_op and payload to the driver behind fd, requiring SQE128 so payload fills the big sqe area,
+     with CQE32 the driver's extra result comes back in big_cqe. Fails with EINVAL without SQE128 and EBUSY on a full sq */
+  #[inline]
+	pub fn uring_cmd(&mut self, fd: c_int, cmd_op: u32, payload: &[u8; 80]) -> Result<&mut io_uring::sqe<T>, Error> {
+    if size_of::<T>() < payload.len() {
+      return Err(Error::from_raw_os_error(EINVAL));
+    }
+
+    let sqe = match self.sq.prep(IORING_OP_URING_CMD, fd, NULL, 0, cmd_op as u64, 0) {
+      Some(sqe) => sqe,
+      None => return Err(Error::from_raw_os_error(EBUSY)),
+    };
+
+    unsafe { ptr::copy_nonoverlapping(payload.as_ptr(), &mut sqe.addr3 as *mut T as *mut u8, payload.len()) };
+
+    Ok(sqe)
+  }
 }
\ No newline at end of file
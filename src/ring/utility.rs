@@ -2,20 +2,22 @@ use std::io::Error;
 use std::ptr;
 use std::mem::size_of;
 use std::ffi::c_void;
-use libc::{sigset_t, EAGAIN};
+use std::time::{Duration, Instant};
+use libc::{sigset_t, EAGAIN, EBUSY, ETIME};
 
 use crate::io_uring::{self, *};
 use crate::ring::Ring;
+use crate::cqueue::{Completions, CqeGuard};
 
 impl<T: Sized, U: Sized> Ring<T, U> {
   pub fn next(&mut self) {
     self.cq.advance(1);
   }
 
-  pub fn wait(&mut self) -> Result<&mut io_uring::cqe<U>, Error> {
+  pub fn wait(&mut self) -> Result<CqeGuard<'_, U>, Error> {
     loop {
       if let Some(cqe) = self.cq.next() {
-        return Ok(unsafe { cqe.as_mut().unwrap() });
+        return Ok(CqeGuard::new(&mut self.cq, cqe));
       };
       match self.ready(0, 1, ptr::null::<sigset_t>(), 0) {
         Ok(_) => (),
@@ -25,6 +27,8 @@ impl<T: Sized, U: Sized> Ring<T, U> {
   }
 
   pub fn submit(&mut self) -> Result<i32, Error> {
+    self.check_overflow()?;
+
     let to_submit = self.sq.remaining();
 
     self.sq.update();
@@ -32,23 +36,185 @@ impl<T: Sized, U: Sized> Ring<T, U> {
     self.ready(to_submit, 0, ptr::null_mut::<sigset_t>(), 0)
   }
 
-  pub fn submit_wait(&mut self) -> Result<&mut io_uring::cqe<U>, Error> {
+  pub fn submit_wait(&mut self) -> Result<CqeGuard<'_, U>, Error> {
+    self.check_overflow()?;
+
     let to_submit = self.sq.remaining();
 
     self.sq.update();
     self.ready(to_submit, 1, ptr::null::<sigset_t>(), 0)?;
 
-    return match self.cq.next() {
-      Some(cqe) => Ok(unsafe { cqe.as_mut().unwrap() }),
+    return match self.cq.guard() {
+      Some(cqe) => Ok(cqe),
       None => Err(Error::last_os_error()),
     };
   }
 
+  /* Submits and waits until at least min_complete completions are ready */
+  pub fn submit_and_wait(&mut self, min_complete: u32) -> Result<u32, Error> {
+    self.check_overflow()?;
+
+    let to_submit = self.sq.remaining();
+
+    self.sq.update();
+    self.ready(to_submit, min_complete, ptr::null::<sigset_t>(), 0)?;
+
+    /* The kernel returns early whenever a timeout fires, so keep waiting until enough are ready */
+    loop {
+      let ready = self.cq.pending();
+
+      if ready >= min_complete {
+        return Ok(ready);
+      }
+
+      self.ready(0, self.cq.available() - ready + min_complete, ptr::null::<sigset_t>(), 0)?;
+    }
+  }
+
+  /* Number of completions the kernel dropped because the cq was full */
+  pub fn overflow(&self) -> u32 {
+    self.cq.overflow()
+  }
+
+  /* Whether the kernel holds overflowed completions that have yet to be flushed into the cq */
+  pub fn backlogged(&self) -> bool {
+    self.sq.cq_overflow()
+  }
+
+  /* Moves backlogged completions into the cq as space allows */
+  pub fn flush(&mut self) -> Result<i32, Error> {
+    io_uring::enter(self.enter_fd, 0, 0, IORING_ENTER_GETEVENTS, ptr::null::<sigset_t>())
+  }
+
+  /* Without NODROP an overflowing cq loses completions, so submissions fail with EBUSY until it drains */
+  pub(crate) fn check_overflow(&self) -> Result<(), Error> {
+    if (self.features & IORING_FEAT_NODROP) == 0 && (self.sq.cq_overflow() || self.cq.is_full()) {
+      return Err(Error::from_raw_os_error(EBUSY));
+    }
+
+    Ok(())
+  }
+
+  /* Yields every ready completion and advances the head once when dropped */
+  pub fn completions(&mut self) -> Completions<'_, U> {
+    self.cq.completions()
+  }
+
+  /* Waits for a completion, failing with ETIME once the timeout elapses */
+  pub fn wait_timeout(&mut self, timeout: Duration) -> Result<CqeGuard<'_, U>, Error> {
+    if self.cq.next().is_none() {
+      self.wait_deadline(0, 1, timeout)?;
+    }
+
+    return match self.cq.guard() {
+      Some(cqe) => Ok(cqe),
+      None => Err(Error::from_raw_os_error(ETIME)),
+    };
+  }
+
+  /* Submits and waits for min_complete completions, failing with ETIME once the timeout elapses */
+  pub fn submit_and_wait_timeout(&mut self, min_complete: u32, timeout: Duration) -> Result<u32, Error> {
+    self.check_overflow()?;
+
+    let to_submit = self.sq.remaining();
+
+    self.sq.update();
+    self.wait_deadline(to_submit, min_complete, timeout)
+  }
+
+  pub(crate) fn wait_deadline(&mut self, to_submit: u32, min_complete: u32, timeout: Duration) -> Result<u32, Error> {
+    if (self.features & IORING_FEAT_EXT_ARG) > 0 {
+      let deadline = Instant::now() + timeout;
+      let mut to_submit = to_submit;
+
+      loop {
+        let ready = self.cq.pending();
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        if ready >= min_complete && to_submit == 0 {
+          return Ok(ready);
+        }
+        if remaining.is_zero() && to_submit == 0 {
+          return Err(Error::from_raw_os_error(ETIME));
+        }
+
+        let ts = __kernel_timespec::from_duration(remaining);
+
+        self.ready_timeout(to_submit, self.cq.available() - ready + min_complete, ptr::null::<sigset_t>(), Some(&ts))?;
+        to_submit = 0;
+      }
+    }
+
+    /* Without EXT_ARG the deadline is an internal timeout sqe, so only arm one when there is something to wait for */
+    let ready = self.cq.pending();
+
+    if to_submit == 0 && ready >= min_complete {
+      return Ok(ready);
+    }
+
+    let wait = self.cq.add_wait();
+
+    match self.prep_timespec(IORING_OP_TIMEOUT, -1, timeout, 1, 0, 0) {
+      Some(sqe) => sqe.user_data = wait,
+      None => {
+        self.cq.drop_wait(wait);
+        return Err(Error::from_raw_os_error(EBUSY));
+      },
+    };
+
+    let mut to_submit = to_submit + 1;
+
+    self.sq.update();
+
+    loop {
+      let ready = self.cq.pending();
+
+      if ready >= min_complete {
+        self.disarm_wait(wait)?;
+        return Ok(ready);
+      }
+      if self.cq.posted(wait) {
+        return Err(Error::from_raw_os_error(ETIME));
+      }
+
+      self.ready(to_submit, self.cq.available() + 1, ptr::null::<sigset_t>(), 0)?;
+      to_submit = 0;
+    }
+  }
+
+  /* Removes an internal timeout still armed once its wait is over, if the sq is full it is left to expire unseen */
+  fn disarm_wait(&mut self, wait: u64) -> Result<(), Error> {
+    if !self.cq.waits.contains(&wait) || self.cq.posted(wait) {
+      return Ok(());
+    }
+
+    let remove = self.cq.add_wait();
+
+    match self.timeout_remove(wait, 0) {
+      Some(sqe) => sqe.user_data = remove,
+      None => {
+        self.cq.drop_wait(remove);
+        return Ok(());
+      },
+    };
+
+    self.sq.update();
+    self.ready(1, 0, ptr::null::<sigset_t>(), 0)?;
+
+    Ok(())
+  }
+
   pub fn ready(&mut self, to_submit: u32, min_complete: u32, sig: *const sigset_t, timeout: u32) -> Result<i32, Error> {
+    let ts = __kernel_timespec::from_ms(timeout as i64);
+
+    self.ready_timeout(to_submit, min_complete, sig, if timeout > 0 { Some(&ts) } else { None })
+  }
+
+  pub(crate) fn ready_timeout(&mut self, to_submit: u32, min_complete: u32, sig: *const sigset_t, ts: Option<&__kernel_timespec>) -> Result<i32, Error> {
     let sqpoll = (self.flags & IORING_SETUP_SQPOLL) > 0;
     let iopoll = (self.flags & IORING_SETUP_IOPOLL) > 0;
     let wakeup = self.sq.needs_wakeup();
-    let flush = self.cq.needs_flush();
+    let flush = self.sq.needs_flush();
     let sq_enter = (wakeup || !sqpoll) && (to_submit > 0);
     let cq_enter = iopoll || flush;
 
@@ -59,21 +225,20 @@ impl<T: Sized, U: Sized> Ring<T, U> {
       let mut flags = 0;
       let available = self.cq.available();
 
-      if min_complete > available {
+      if min_complete > available || cq_enter {
         flags |= IORING_ENTER_GETEVENTS
       }
       if wakeup {
         flags |= IORING_ENTER_SQ_WAKEUP;
       }
-      if (self.features & IORING_FEAT_EXT_ARG) > 0 && timeout > 0 {
-        let ts = __kernel_timespec::from_ms(timeout as i64);
-        let arg = io_uring::getevents_arg::new(sig, &ts);
+      if let Some(ts) = ts.filter(|_| (self.features & IORING_FEAT_EXT_ARG) > 0) {
+        let arg = io_uring::getevents_arg::new(sig, ts);
         let ptr = &arg as *const io_uring::getevents_arg as *const c_void;
         let size = size_of::<io_uring::getevents_arg>();
   
         flags |= IORING_ENTER_EXT_ARG;
   
-        return io_uring::enter2(self.enter_fd, 0, min_complete, flags, ptr, size);
+        return io_uring::enter2(self.enter_fd, to_submit, min_complete, flags, ptr, size);
       }
 
       return io_uring::enter(self.enter_fd, to_submit, min_complete, flags, sig);
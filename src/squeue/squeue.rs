@@ -24,7 +24,7 @@ impl<T: Sized> SQueue<T> {
     SQueue {
       khead: ring.add(p.sq_off.head as usize)            as *mut AtomicU32,
       ktail: ring.add(p.sq_off.tail as usize)            as *mut AtomicU32,
-      kflags: ring.add(p.sq_off.tail as usize)           as *mut AtomicU32,
+      kflags: ring.add(p.sq_off.flags as usize)          as *mut AtomicU32,
       kdropped: ring.add(p.sq_off.dropped as usize)      as *mut AtomicU32,
       array: ring.add(p.sq_off.array as usize).cast::<u32>(),
       sqes: sqes,
@@ -43,6 +43,15 @@ impl<T: Sized> SQueue<T> {
     tail - head
   }
 
+  /* Entries published to the kernel that it has not consumed yet */
+  #[inline]
+  pub(crate) fn unconsumed(&self) -> u32 {
+    let tail = unsafe { (*self.ktail).load(Ordering::Acquire) };
+    let head = unsafe { (*self.khead).load(Ordering::Acquire) };
+
+    tail - head
+  }
+
   #[inline]
   pub(crate) fn needs_wakeup(&self) -> bool {
     unsafe { 
@@ -50,6 +59,29 @@ impl<T: Sized> SQueue<T> {
     }
   }
 
+  /* The kernel sets these flags in the sq ring when it holds back completions */
+  #[inline]
+  pub(crate) fn needs_flush(&self) -> bool {
+    let flags = IORING_SQ_CQ_OVERFLOW | IORING_SQ_TASKRUN;
+
+    unsafe {
+      ((*self.kflags).load(Ordering::Acquire) & flags) > 0
+    }
+  }
+
+  #[inline]
+  pub(crate) fn cq_overflow(&self) -> bool {
+    unsafe {
+      ((*self.kflags).load(Ordering::Acquire) & IORING_SQ_CQ_OVERFLOW) > 0
+    }
+  }
+
+  /* Index of the slot the next sqe is taken from */
+  #[inline]
+  pub(crate) fn slot(&self) -> usize {
+    (self.sqe_tail & self.ring_mask) as usize
+  }
+
   pub(crate) fn update(&mut self) {
     if self.sqe_head != self.sqe_tail {      
       self.sqe_head = self.sqe_tail;
@@ -59,8 +91,7 @@ impl<T: Sized> SQueue<T> {
   }
 
   pub(crate) fn next(&mut self) -> Option<*mut io_uring::sqe<T>> {
-    let shift = if size_of::<sqe<T>>() == 128 { 1 } else { 0 };
-    let index = (self.sqe_tail & self.ring_mask) << shift;
+    let index = self.sqe_tail & self.ring_mask;
     let next = self.sqe_tail + 1;
     let head = unsafe { (*self.khead).load(Ordering::Acquire) };
 
@@ -1,5 +1,7 @@
 mod map;
+mod slab;
 
 pub(crate) use {
   map::Map,
+  slab::Slab,
 };
\ No newline at end of file
@@ -0,0 +1,63 @@
+/* Vec backed storage handing out reusable keys */
+#[derive(Debug)]
+pub struct Slab<T> {
+  entries: Vec<Option<T>>,
+  free:    Vec<usize>,
+  len:     usize,
+}
+
+impl<T> Slab<T> {
+  pub fn new() -> Self {
+    Slab {
+      entries: Vec::new(),
+      free: Vec::new(),
+      len: 0,
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  pub fn insert(&mut self, value: T) -> usize {
+    self.len += 1;
+
+    match self.free.pop() {
+      Some(key) => {
+        self.entries[key] = Some(value);
+        key
+      },
+      None => {
+        self.entries.push(Some(value));
+        self.entries.len() - 1
+      },
+    }
+  }
+
+  pub fn get(&self, key: usize) -> Option<&T> {
+    self.entries.get(key)?.as_ref()
+  }
+
+  pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+    self.entries.get_mut(key)?.as_mut()
+  }
+
+  pub fn remove(&mut self, key: usize) -> Option<T> {
+    let value = self.entries.get_mut(key)?.take()?;
+
+    self.free.push(key);
+    self.len -= 1;
+
+    Some(value)
+  }
+}
+
+impl<T> Default for Slab<T> {
+  fn default() -> Self {
+    Slab::new()
+  }
+}
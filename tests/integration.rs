@@ -0,0 +1,1056 @@
+use rsring::{RSRing, Completions, CqeGuard};
+
+#[test]
+fn batch_completions() {
+  let mut ring = RSRing::new(32).unwrap();
+
+  for i in 0..8 {
+    ring.nop().unwrap().set_data_u64(i);
+  }
+
+  assert!(ring.submit_and_wait(8).unwrap() >= 8);
+
+  let completions: Completions<'_, [u8; 0]> = ring.completions();
+  let data: Vec<u64> = completions.map(|cqe| cqe.get_data_u64()).collect();
+
+  assert_eq!(data, (0..8).collect::<Vec<u64>>());
+  assert_eq!(ring.completions().count(), 0);
+}
+
+#[test]
+fn partial_completions() {
+  let mut ring = RSRing::new(32).unwrap();
+
+  for i in 0..4 {
+    ring.nop().unwrap().set_data_u64(i);
+  }
+  ring.submit_and_wait(4).unwrap();
+
+  assert_eq!(ring.completions().take(1).count(), 1);
+  assert_eq!(ring.completions().next().unwrap().get_data_u64(), 1);
+  assert_eq!(ring.completions().count(), 2);
+}
+
+#[test]
+fn guard_advances_once() {
+  let mut ring = RSRing::new(32).unwrap();
+
+  ring.nop().unwrap().set_data_u64(1);
+  ring.nop().unwrap().set_data_u64(2);
+  ring.submit_and_wait(2).unwrap();
+
+  let cqe = ring.wait().unwrap().keep();
+
+  assert_eq!(cqe.get_data_u64(), 1);
+
+  let guard: CqeGuard<'_, [u8; 0]> = ring.wait().unwrap();
+
+  assert_eq!(guard.get_data_u64(), 1);
+  drop(guard);
+  assert_eq!(ring.wait().unwrap().get_data_u64(), 2);
+  assert_eq!(ring.completions().count(), 0);
+}
+
+#[test]
+fn eventfd_notifications() {
+  let mut ring = RSRing::new(32).unwrap();
+  let efd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK) };
+  let mut count = 0u64;
+  let read = |count: &mut u64| unsafe { libc::read(efd, count as *mut u64 as *mut libc::c_void, 8) };
+
+  ring.register_eventfd(efd).unwrap();
+  ring.nop().unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(read(&mut count), 8);
+  assert!(count >= 1);
+  assert_eq!(ring.completions().count(), 1);
+
+  ring.set_eventfd_enabled(false);
+  ring.nop().unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  assert!(!ring.eventfd_enabled());
+  assert_eq!(read(&mut count), -1);
+  assert_eq!(ring.completions().count(), 1);
+
+  ring.set_eventfd_enabled(true);
+  ring.unregister_eventfd().unwrap();
+  ring.register_eventfd_async(efd).unwrap();
+  ring.unregister_eventfd().unwrap();
+
+  unsafe { libc::close(efd) };
+}
+
+#[test]
+fn dispatch_handlers() {
+  use std::{rc::Rc, cell::RefCell};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let seen = Rc::new(RefCell::new(Vec::new()));
+
+  for i in 0..3 {
+    let seen = seen.clone();
+
+    ring.prep_once(|ring| ring.nop(), move |cqe| seen.borrow_mut().push((i, cqe.res))).unwrap();
+  }
+
+  let seen_multi = seen.clone();
+  let token = ring.prep_multi(|ring| ring.nop(), move |cqe| seen_multi.borrow_mut().push((9, cqe.res))).unwrap();
+
+  ring.nop().unwrap().set_data_u64(token.user_data() & 1);
+
+  assert_eq!(ring.dispatch_pending(), 4);
+  assert!(ring.submit_and_wait(5).unwrap() >= 5);
+  assert_eq!(ring.dispatch(), 4);
+  assert_eq!(*seen.borrow(), vec![(0, 0), (1, 0), (2, 0), (9, 0)]);
+  assert_eq!(ring.dispatch_pending(), 0);
+  assert_eq!(ring.wait().unwrap().get_data_u64(), token.user_data() & 1);
+
+  /* A completion without a token holds back the ones behind it until it is reaped */
+  let seen_once = seen.clone();
+
+  ring.nop().unwrap().set_data_u64(7);
+  ring.prep_once(|ring| ring.nop(), move |cqe| seen_once.borrow_mut().push((3, cqe.res))).unwrap();
+  ring.submit_and_wait(2).unwrap();
+
+  assert_eq!(ring.dispatch(), 0);
+  assert_eq!(ring.wait().unwrap().get_data_u64(), 7);
+  assert_eq!(ring.dispatch(), 1);
+  assert_eq!(seen.borrow().last(), Some(&(3, 0)));
+
+  /* Tagged user_data naming a live slot is still not that slot's token */
+  let seen_once = seen.clone();
+  let mut fds = [0; 2];
+  let mut byte = 0u8;
+
+  unsafe { libc::pipe(fds.as_mut_ptr()) };
+
+  let token = ring.prep_once(|ring| ring.read(fds[0], &mut byte as *mut u8 as *mut libc::c_void, 1), move |cqe| seen_once.borrow_mut().push((4, cqe.res))).unwrap();
+  let foreign = 1 << 63 | (token.user_data() & 0xFFFF_FFFF);
+
+  ring.nop().unwrap().set_data_u64(foreign);
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.dispatch(), 0);
+  assert_eq!(ring.dispatch_pending(), 1);
+  assert_eq!(ring.wait().unwrap().get_data_u64(), foreign);
+
+  unsafe { libc::write(fds[1], b"x".as_ptr() as *const libc::c_void, 1) };
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.dispatch(), 1);
+  assert_eq!(seen.borrow().last(), Some(&(4, 1)));
+
+  unsafe {
+    libc::close(fds[0]);
+    libc::close(fds[1]);
+  };
+}
+
+#[test]
+fn drop_releases_handlers() {
+  use std::{cell::Cell, os::unix::{io::AsRawFd, net::UnixStream}, rc::Rc};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (_tx, rx) = UnixStream::pair().unwrap();
+  let owned = Rc::new(Cell::new(false));
+  let held = owned.clone();
+  let mut buf = vec![0u8; 4];
+  let ptr = buf.as_mut_ptr() as *mut libc::c_void;
+
+  /* The pending recv is cancelled and reaped first, so its handler and what it owns are freed without running */
+  ring.prep_once(|ring| ring.recv(rx.as_raw_fd(), ptr, 4, 0), move |_| {
+    drop(buf);
+    held.set(true);
+  }).unwrap();
+  ring.submit().unwrap();
+  drop(ring);
+
+  assert_eq!(Rc::strong_count(&owned), 1);
+  assert!(!owned.get());
+}
+
+fn temp_file(name: &str) -> (std::fs::File, std::path::PathBuf) {
+  let path = std::env::temp_dir().join(format!("rsring-{}-{}", std::process::id(), name));
+  let file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+
+  (file, path)
+}
+
+#[test]
+fn fixed_buffers() {
+  use std::{io::IoSliceMut, os::fd::AsRawFd, ffi::c_void};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (file, path) = temp_file("fixed");
+  let mut src = *b"registered buffers";
+  let mut dst = [0u8; 18];
+
+  ring.register_buffers_sparse(2).unwrap();
+
+  let err = ring.write_fixed(file.as_raw_fd(), src.as_ptr() as *const c_void, src.len(), 0, 0).unwrap_err();
+
+  assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+  assert_eq!(ring.update_buffers(0, &[IoSliceMut::new(&mut src), IoSliceMut::new(&mut dst)]).unwrap(), 2);
+  assert!(ring.write_fixed(file.as_raw_fd(), src.as_ptr() as *const c_void, src.len() + 1, 0, 0).is_err());
+  assert!(ring.write_fixed(file.as_raw_fd(), src.as_ptr() as *const c_void, src.len(), 0, 2).is_err());
+
+  ring.write_fixed(file.as_raw_fd(), src.as_ptr() as *const c_void, src.len(), 0, 0).unwrap().link();
+  ring.read_fixed(file.as_raw_fd(), dst.as_mut_ptr() as *mut c_void, dst.len(), 0, 1).unwrap();
+  ring.submit_and_wait(2).unwrap();
+
+  for cqe in ring.completions() {
+    assert_eq!(cqe.result().unwrap(), 18);
+  }
+  assert_eq!(&dst, b"registered buffers");
+
+  /* A cleared slot no longer accepts fixed requests */
+  ring.clear_buffer(1).unwrap();
+
+  assert!(ring.read_fixed(file.as_raw_fd(), dst.as_mut_ptr() as *mut c_void, dst.len(), 0, 1).is_err());
+  assert_eq!(ring.clear_buffer(2).unwrap_err().raw_os_error(), Some(libc::EINVAL));
+
+  ring.read_fixed(file.as_raw_fd(), src.as_mut_ptr() as *mut c_void, src.len(), 0, 0).unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().result().unwrap(), 18);
+
+  /* Bypassing the bounds check shows the kernel dropped the buffer too */
+  let sqe = ring.pread(file.as_raw_fd(), dst.as_mut_ptr() as *mut c_void, dst.len(), 0).unwrap();
+
+  sqe.opcode = rsring::constants::IORING_OP_READ_FIXED as u8;
+  sqe.buf_select = 1;
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().res, -libc::EFAULT);
+
+  ring.unregister_buffers().unwrap();
+  ring.register_buffers(&[IoSliceMut::new(&mut dst)]).unwrap();
+  ring.unregister_buffers().unwrap();
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn poll_ops() {
+  use libc::{POLLIN, POLLOUT, ECANCELED};
+  use rsring::constants::*;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let mut fds = [0; 2];
+  let mut byte = 1u8;
+
+  unsafe { libc::pipe(fds.as_mut_ptr()) };
+
+  ring.poll_multishot(fds[0], POLLIN as u32).unwrap().set_data_u64(1);
+  ring.poll_add(fds[1], POLLOUT as u32).unwrap().set_data_u64(2);
+  ring.submit_and_wait(1).unwrap();
+
+  let cqe = ring.wait().unwrap();
+
+  assert_eq!((cqe.get_data_u64(), cqe.has_more()), (2, false));
+  assert!(cqe.result().unwrap() & POLLOUT as u32 > 0);
+  drop(cqe);
+
+  unsafe { libc::write(fds[1], &byte as *const u8 as *const libc::c_void, 1) };
+
+  let cqe = ring.wait().unwrap();
+
+  assert_eq!((cqe.get_data_u64(), cqe.has_more()), (1, true));
+  drop(cqe);
+
+  unsafe { libc::read(fds[0], &mut byte as *mut u8 as *mut libc::c_void, 1) };
+
+  ring.poll_update(1, 3, POLLIN as u32, IORING_POLL_UPDATE_USER_DATA | IORING_POLL_UPDATE_EVENTS | IORING_POLL_ADD_MULTI).unwrap().set_data_u64(4);
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().get_data_u64(), 4);
+
+  ring.poll_remove(3).unwrap().set_data_u64(5);
+
+  assert_eq!(reap(&mut ring, 2), vec![(3, -ECANCELED), (5, 0)]);
+
+  unsafe {
+    libc::close(fds[0]);
+    libc::close(fds[1]);
+  };
+}
+
+fn clock_now(clock: libc::clockid_t) -> std::time::Duration {
+  let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+
+  unsafe { libc::clock_gettime(clock, &mut ts) };
+
+  std::time::Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/* Submits and waits for n completions, returning every ready (user_data, res) sorted */
+fn reap(ring: &mut RSRing, n: u32) -> Vec<(u64, i32)> {
+  ring.submit_and_wait(n).unwrap();
+
+  let mut results: Vec<(u64, i32)> = ring.completions().map(|cqe| (cqe.user_data, cqe.res)).collect();
+
+  results.sort();
+  results
+}
+
+#[test]
+fn timeout_ops() {
+  use std::time::Duration;
+  use libc::{ETIME, ECANCELED, CLOCK_MONOTONIC, CLOCK_REALTIME};
+  use rsring::constants::*;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let ms = Duration::from_millis(5);
+
+  ring.timeout(ms, 0, 0).unwrap().set_data_u64(1);
+  ring.timeout(clock_now(CLOCK_MONOTONIC) + ms, 0, IORING_TIMEOUT_ABS).unwrap().set_data_u64(2);
+  ring.timeout(clock_now(CLOCK_REALTIME) + ms, 0, IORING_TIMEOUT_ABS | IORING_TIMEOUT_REALTIME).unwrap().set_data_u64(3);
+  ring.timeout(ms, 0, IORING_TIMEOUT_BOOTTIME | IORING_TIMEOUT_ETIME_SUCCESS).unwrap().set_data_u64(4);
+
+  assert_eq!(reap(&mut ring, 4), vec![(1, -ETIME), (2, -ETIME), (3, -ETIME), (4, -ETIME)]);
+
+  /* Count based timeouts complete without error once enough requests finish */
+  ring.timeout(Duration::from_secs(10), 1, 0).unwrap().set_data_u64(5);
+  ring.nop().unwrap().set_data_u64(6);
+
+  assert_eq!(reap(&mut ring, 2), vec![(5, 0), (6, 0)]);
+
+  /* A long timeout updated to a short one fires early, a removed one is cancelled */
+  ring.timeout(Duration::from_secs(10), 0, 0).unwrap().set_data_u64(7);
+  ring.timeout(Duration::from_secs(10), 0, 0).unwrap().set_data_u64(8);
+  ring.timeout_update(7, ms, 0).unwrap().set_data_u64(9);
+  ring.timeout_remove(8, 0).unwrap().set_data_u64(10);
+
+  assert_eq!(reap(&mut ring, 4), vec![(7, -ETIME), (8, -ECANCELED), (9, 0), (10, 0)]);
+}
+
+#[test]
+fn async_cancel_ops() {
+  use std::ffi::c_void;
+  use libc::ECANCELED;
+  use rsring::constants::*;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let mut fds = [0; 2];
+  let mut buf = [0u8; 4];
+
+  unsafe { libc::pipe(fds.as_mut_ptr()) };
+
+  for i in 1..=3 {
+    ring.read(fds[0], buf.as_mut_ptr() as *mut c_void, buf.len()).unwrap().set_data_u64(i);
+  }
+  ring.submit().unwrap();
+  ring.async_cancel(1, 0).unwrap().set_data_u64(10);
+
+  assert_eq!(reap(&mut ring, 2), vec![(1, -ECANCELED), (10, 0)]);
+
+  ring.async_cancel_fd(fds[0], IORING_ASYNC_CANCEL_ALL).unwrap().set_data_u64(11);
+
+  assert_eq!(reap(&mut ring, 3), vec![(2, -ECANCELED), (3, -ECANCELED), (11, 2)]);
+
+  ring.read(fds[0], buf.as_mut_ptr() as *mut c_void, buf.len()).unwrap().set_data_u64(4);
+  ring.poll_add(fds[0], libc::POLLIN as u32).unwrap().set_data_u64(5);
+  ring.submit().unwrap();
+  ring.async_cancel_any().unwrap().set_data_u64(12);
+
+  assert_eq!(reap(&mut ring, 3), vec![(4, -ECANCELED), (5, -ECANCELED), (12, 2)]);
+
+  unsafe {
+    libc::close(fds[0]);
+    libc::close(fds[1]);
+  };
+}
+
+#[test]
+fn sync_cancel() {
+  use std::{ffi::c_void, time::Duration};
+  use libc::ECANCELED;
+  use rsring::CancelMatch;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let mut fds = [0; 2];
+  let mut buf = [0u8; 4];
+
+  unsafe { libc::pipe(fds.as_mut_ptr()) };
+
+  for i in 1..=3 {
+    ring.read(fds[0], buf.as_mut_ptr() as *mut c_void, buf.len()).unwrap().set_data_u64(i);
+  }
+
+  /* Requests are issued in order, so the reads are in flight once the nop completes */
+  ring.nop().unwrap().set_data_u64(0);
+  ring.submit_and_wait(1).unwrap();
+
+  ring.cancel_sync(CancelMatch::UserData(1), Some(Duration::from_secs(1))).unwrap();
+
+  assert_eq!(reap(&mut ring, 2), vec![(0, 0), (1, -ECANCELED)]);
+
+  ring.cancel_sync(CancelMatch::Fd(fds[0]), None).unwrap();
+
+  assert_eq!(reap(&mut ring, 2), vec![(2, -ECANCELED), (3, -ECANCELED)]);
+  assert_eq!(ring.cancel_sync(CancelMatch::Any, None).unwrap(), 0);
+
+  unsafe {
+    libc::close(fds[0]);
+    libc::close(fds[1]);
+  };
+}
+
+#[test]
+fn deadlines() {
+  use std::{ffi::c_void, io::Write, mem, net::TcpListener, os::unix::{io::AsRawFd, net::UnixStream}, time::Duration};
+  use libc::{ETIME, ECANCELED};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (mut tx, rx) = UnixStream::pair().unwrap();
+  let mut buf = [0u8; 4];
+  let ptr = buf.as_mut_ptr() as *mut c_void;
+  let fd = rx.as_raw_fd();
+
+  /* A recv cut short reports a single -ETIME, the link timeout's own completion is never handed out */
+  ring.with_deadline(Duration::from_millis(10), |ring| ring.recv(fd, ptr, 4, 0).map(|sqe| sqe.set_data_u64(1))).unwrap();
+
+  assert_eq!(reap(&mut ring, 1), vec![(1, -ETIME)]);
+
+  ring.with_deadline(Duration::from_secs(10), |ring| ring.recv(fd, ptr, 4, 0).map(|sqe| sqe.set_data_u64(2))).unwrap();
+  ring.submit().unwrap();
+  tx.write_all(b"ping").unwrap();
+
+  assert_eq!(reap(&mut ring, 1), vec![(2, 4)]);
+
+  /* Cancelling the request itself still reports -ECANCELED */
+  ring.with_deadline(Duration::from_secs(10), |ring| ring.recv(fd, ptr, 4, 0).map(|sqe| sqe.set_data_u64(3))).unwrap();
+  ring.submit().unwrap();
+  ring.async_cancel(3, 0).unwrap().set_data_u64(4);
+
+  assert_eq!(reap(&mut ring, 2), vec![(3, -ECANCELED), (4, 0)]);
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sockfd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+  let mut addr: libc::sockaddr_in = unsafe { mem::zeroed() };
+
+  addr.sin_family = libc::AF_INET as libc::sa_family_t;
+  addr.sin_port = listener.local_addr().unwrap().port().to_be();
+  addr.sin_addr.s_addr = u32::from_be_bytes([127, 0, 0, 1]).to_be();
+
+  let sa = &addr as *const libc::sockaddr_in as *const libc::sockaddr;
+  let len = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+
+  ring.with_deadline(Duration::from_secs(10), |ring| ring.connect(sockfd, sa, len).map(|sqe| sqe.set_data_u64(5))).unwrap();
+
+  assert_eq!(reap(&mut ring, 1), vec![(5, 0)]);
+  assert_eq!(ring.wait_timeout(Duration::from_millis(20)).unwrap_err().raw_os_error(), Some(ETIME));
+
+  /* Only the ids handed out for deadlines are internal, the rest of that range is ordinary user_data */
+  ring.nop().unwrap().set_data_u64(0xFFFF_FFFF_0000_0000);
+  ring.nop().unwrap().set_data_u64(u64::MAX - 5);
+  ring.nop().unwrap().set_data_u64(u64::MAX);
+
+  assert_eq!(reap(&mut ring, 3), vec![(0xFFFF_FFFF_0000_0000, 0), (u64::MAX - 5, 0), (u64::MAX, 0)]);
+
+  unsafe { libc::close(sockfd) };
+}
+
+#[test]
+fn deadlines_shared_user_data() {
+  use std::{ffi::c_void, os::unix::{io::AsRawFd, net::UnixStream}, time::Duration};
+  use libc::{ETIME, ECANCELED};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (_tx, rx) = UnixStream::pair().unwrap();
+  let (_tx2, rx2) = UnixStream::pair().unwrap();
+  let mut buf = [0u8; 8];
+  let ptr = buf.as_mut_ptr() as *mut c_void;
+  let (long, short) = (rx.as_raw_fd(), rx2.as_raw_fd());
+
+  /* The short deadline fires while the long one is still pending on the same user_data */
+  ring.with_deadline(Duration::from_secs(10), |ring| ring.recv(long, ptr, 4, 0).map(|sqe| sqe.set_data_u64(0))).unwrap();
+  ring.with_deadline(Duration::from_millis(10), |ring| ring.recv(short, unsafe { ptr.add(4) }, 4, 0).map(|sqe| sqe.set_data_u64(0))).unwrap();
+
+  assert_eq!(reap(&mut ring, 1), vec![(0, -ETIME)]);
+
+  /* Cancelling the other one is not mistaken for its deadline */
+  ring.async_cancel(0, 0).unwrap().set_data_u64(5);
+
+  assert_eq!(reap(&mut ring, 2), vec![(0, -ECANCELED), (5, 0)]);
+}
+
+#[test]
+fn fixed_files() {
+  use std::ffi::c_void;
+  use rsring::constants::*;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let mut fds = [0; 2];
+  let mut buf = [0u8; 4];
+
+  unsafe { libc::pipe(fds.as_mut_ptr()) };
+
+  ring.register_files_sparse(8).unwrap();
+
+  assert_eq!((ring.files().len(), ring.files().free()), (8, 8));
+
+  /* Slot 0 is filled synchronously, slot 1 through an sqe */
+  assert_eq!(ring.update_files(0, &[fds[0]]).unwrap(), 1);
+
+  let mut update = [fds[1]];
+
+  ring.files_update(&mut update, 1).unwrap().set_data_u64(1);
+  ring.submit_and_wait(1).unwrap();
+
+  assert!(ring.files().is_free(1));
+
+  let res = ring.wait().unwrap().res;
+
+  assert_eq!(res, 1);
+  ring.files_updated(1, &update, res);
+  assert!(!ring.files().is_free(0) && !ring.files().is_free(1));
+
+  /* A failed update leaves the slot free */
+  let mut bad = [-5];
+
+  ring.files_update(&mut bad, 3).unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  let res = ring.wait().unwrap().res;
+
+  assert_eq!(res, -libc::EBADF);
+  ring.files_updated(3, &bad, res);
+  assert!(ring.files().is_free(3));
+
+  ring.write(1, b"ping".as_ptr() as *const c_void, 4).unwrap().fixed_file();
+  ring.read(0, buf.as_mut_ptr() as *mut c_void, 4).unwrap().fixed_file();
+  ring.submit_and_wait(2).unwrap();
+
+  let results: Vec<i32> = ring.completions().map(|cqe| cqe.res).collect();
+
+  assert_eq!(results, vec![4, 4]);
+  assert_eq!(&buf, b"ping");
+
+  /* Auto-allocation stays within the range, reserved slots come from outside it */
+  ring.set_file_alloc_range(4, 4).unwrap();
+
+  let mut alloc = [fds[0]];
+
+  ring.files_update(&mut alloc, IORING_FILE_INDEX_ALLOC).unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  let res = ring.wait().unwrap().res;
+
+  assert_eq!(res, 1);
+  assert!((4..8).contains(&alloc[0]));
+  ring.files_updated(IORING_FILE_INDEX_ALLOC, &alloc, res);
+  assert!(!ring.files().is_free(alloc[0] as u32));
+  assert_eq!(ring.reserve_file_slot(), Some(2));
+
+  ring.release_file_slot(2);
+  ring.update_files(0, &[-1, IORING_REGISTER_FILES_SKIP]).unwrap();
+
+  assert!(ring.files().is_free(0) && !ring.files().is_free(1));
+
+  ring.unregister_files().unwrap();
+
+  assert!(ring.files().is_empty());
+
+  unsafe {
+    libc::close(fds[0]);
+    libc::close(fds[1]);
+  };
+}
+
+#[test]
+fn provided_buffers() {
+  use std::{ffi::c_void, io::Write, os::unix::{io::AsRawFd, net::UnixStream}, ptr};
+  use libc::ENOBUFS;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (mut tx, rx) = UnixStream::pair().unwrap();
+  let mut fds = [0; 2];
+  let mut pool = vec![0u8; 4 * 8];
+
+  unsafe { libc::pipe(fds.as_mut_ptr()) };
+
+  ring.provide_buffers(pool.as_mut_ptr() as *mut c_void, 8, 4, 7, 0).unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().res, 0);
+
+  /* The kernel picks a buffer for each request and reports its id in the completion */
+  tx.write_all(b"ping").unwrap();
+  unsafe { libc::write(fds[1], b"pong".as_ptr() as *const c_void, 4) };
+
+  ring.recv(rx.as_raw_fd(), ptr::null_mut(), 8, 0).unwrap().buffer_select(7).set_data_u64(1);
+  ring.read(fds[0], ptr::null_mut(), 8).unwrap().buffer_select(7).set_data_u64(2);
+  ring.submit_and_wait(2).unwrap();
+
+  let mut results: Vec<(u64, i32, u16)> = ring.completions().map(|cqe| (cqe.get_data_u64(), cqe.res, cqe.buffer_id().unwrap())).collect();
+
+  results.sort();
+
+  for (data, res, bid) in &results {
+    let chunk = &pool[*bid as usize * 8..][..*res as usize];
+
+    assert_eq!(chunk, if *data == 1 { b"ping" } else { b"pong" });
+  }
+  assert_ne!(results[0].2, results[1].2);
+
+  /* Only the untouched buffers are left to remove, after which selection runs dry */
+  ring.remove_buffers(4, 7).unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().res, 2);
+
+  ring.recv(rx.as_raw_fd(), ptr::null_mut(), 8, 0).unwrap().buffer_select(7);
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().res, -ENOBUFS);
+
+  unsafe {
+    libc::close(fds[0]);
+    libc::close(fds[1]);
+  };
+}
+
+#[test]
+fn buf_ring_recv() {
+  use std::{io::Write, os::unix::{io::AsRawFd, net::UnixStream}, ptr};
+  use libc::ENOBUFS;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (mut tx, rx) = UnixStream::pair().unwrap();
+  let mut br = ring.register_buf_ring(2, 16, 3).unwrap();
+
+  ring.recv(rx.as_raw_fd(), ptr::null_mut(), 0, 0).unwrap().multishot().buffer_select(br.bgid()).set_data_u64(1);
+  ring.submit().unwrap();
+
+  /* Each message lands in its own buffer, recycled ones are picked again */
+  for msg in [b"one", b"two", b"six"] {
+    tx.write_all(msg).unwrap();
+
+    let cqe = ring.wait().unwrap();
+    let (bid, data) = br.buffer(&cqe).unwrap();
+
+    assert_eq!(data, msg);
+    assert!(cqe.has_more());
+
+    drop(cqe);
+    br.recycle(bid).unwrap();
+  }
+
+  /* With every buffer held the multishot recv terminates */
+  tx.write_all(b"ten").unwrap();
+
+  let first = ring.wait().unwrap().buffer_id();
+
+  tx.write_all(b"red").unwrap();
+
+  let second = ring.wait().unwrap().buffer_id();
+
+  assert_ne!(first, second);
+
+  tx.write_all(b"end").unwrap();
+
+  let cqe = ring.wait().unwrap();
+
+  assert_eq!((cqe.res, cqe.has_more()), (-ENOBUFS, false));
+
+  drop(cqe);
+  ring.unregister_buf_ring(br).unwrap();
+
+  /* Dropping a buffer ring unregisters its group, another ring hands it back untouched */
+  drop(ring.register_buf_ring(2, 16, 3).unwrap());
+
+  let br = ring.register_buf_ring(2, 16, 3).unwrap();
+  let (br, err) = RSRing::new(8).unwrap().unregister_buf_ring(br).unwrap_err();
+
+  assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+  ring.unregister_buf_ring(br).unwrap();
+}
+
+#[test]
+fn buf_ring_outlives_ring() {
+  use libc::EEXIST;
+
+  /* A buffer ring dropped after its ring only unregisters from that ring, not one reusing its fd */
+  let mut ring = RSRing::new(8).unwrap();
+  let stale = ring.register_buf_ring(2, 16, 1).unwrap();
+
+  drop(ring);
+
+  let mut ring = RSRing::new(8).unwrap();
+  let br = ring.register_buf_ring(2, 16, 1).unwrap();
+
+  drop(stale);
+
+  assert_eq!(ring.register_buf_ring(2, 16, 1).unwrap_err().raw_os_error(), Some(EEXIST));
+  ring.unregister_buf_ring(br).unwrap();
+}
+
+#[test]
+fn buf_ring_bounds() {
+  let mut ring = RSRing::new(32).unwrap();
+  let mut br = ring.register_buf_ring(2, 16, 5).unwrap();
+
+  /* Buffer ids past the ring are refused rather than indexed */
+  assert_eq!(br.recycle(2).unwrap_err().raw_os_error(), Some(libc::EINVAL));
+  assert!(br.get(2, 0, 16).is_none());
+  assert_eq!(br.get(1, 0, 16).unwrap().len(), 16);
+  ring.unregister_buf_ring(br).unwrap();
+}
+
+#[test]
+fn buf_ring_incremental() {
+  use std::{io::Write, os::unix::{io::AsRawFd, net::UnixStream}, ptr};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (mut tx, rx) = UnixStream::pair().unwrap();
+  let mut br = ring.register_buf_ring_inc(2, 16, 4).unwrap();
+
+  ring.recv(rx.as_raw_fd(), ptr::null_mut(), 0, 0).unwrap().multishot().buffer_select(br.bgid()).set_data_u64(1);
+  ring.submit().unwrap();
+
+  /* Small messages share one buffer until it fills up */
+  for (msg, more) in [(&b"abcd"[..], true), (b"efgh", true), (b"ijklmnop", false)] {
+    tx.write_all(msg).unwrap();
+
+    let cqe = ring.wait().unwrap();
+    let (bid, data) = br.buffer(&cqe).unwrap();
+
+    assert_eq!((bid, data, cqe.buf_more()), (0, msg, more));
+  }
+
+  assert_eq!(br.offset(0), Some(0));
+  assert_eq!(br.get(0, 0, 16).unwrap(), b"abcdefghijklmnop");
+
+  br.recycle(0).unwrap();
+  tx.write_all(b"qr").unwrap();
+
+  let cqe = ring.wait().unwrap();
+
+  assert_eq!(br.buffer(&cqe).unwrap(), (1, &b"qr"[..]));
+  assert_eq!(br.offset(1), Some(2));
+
+  drop(cqe);
+  ring.unregister_buf_ring(br).unwrap();
+}
+
+#[test]
+fn buf_ring_foreign_completion() {
+  use std::{ffi::c_void, io::Write, os::unix::{io::AsRawFd, net::UnixStream}, ptr};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (mut tx, rx) = UnixStream::pair().unwrap();
+  let mut br = ring.register_buf_ring_inc(2, 16, 4).unwrap();
+  let mut pool = vec![0u8; 8 * 4];
+
+  /* A completion from a classic group names buffer ids this ring does not have */
+  ring.provide_buffers(pool.as_mut_ptr() as *mut c_void, 8, 4, 7, 2).unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().res, 0);
+
+  tx.write_all(b"ping").unwrap();
+  ring.recv(rx.as_raw_fd(), ptr::null_mut(), 8, 0).unwrap().buffer_select(7);
+  ring.submit_and_wait(1).unwrap();
+
+  let cqe = ring.wait().unwrap();
+  let bid = cqe.buffer_id().unwrap();
+
+  assert!(bid >= 2);
+  assert!(br.buffer(&cqe).is_none());
+  assert!(br.offset(bid).is_none());
+
+  drop(cqe);
+  ring.unregister_buf_ring(br).unwrap();
+}
+
+#[test]
+fn msg_ring() {
+  use std::{ffi::c_void, os::fd::AsRawFd};
+  use rsring::constants::*;
+
+  let mut src = RSRing::new(32).unwrap();
+  let mut dst = RSRing::new(32).unwrap();
+  let mut fds = [0; 2];
+  let mut buf = [0u8; 4];
+
+  unsafe { libc::pipe(fds.as_mut_ptr()) };
+
+  /* Data messages land in the target ring, the sender seeing its own completion for each */
+  src.msg_ring(dst.as_raw_fd(), 7, 42, 0).unwrap().set_data_u64(1);
+  src.msg_ring_cqe_flags(dst.as_raw_fd(), 8, 43, 0, IORING_CQE_F_MORE).unwrap().set_data_u64(2);
+
+  assert_eq!(reap(&mut src, 2), vec![(1, 0), (2, 0)]);
+
+  dst.submit_and_wait(2).unwrap();
+
+  let results: Vec<(u64, i32, u32)> = dst.completions().map(|cqe| (cqe.user_data, cqe.res, cqe.flags)).collect();
+
+  assert_eq!(results, vec![(42, 7, 0), (43, 8, IORING_CQE_F_MORE)]);
+
+  /* A fixed file moves into a chosen or allocated slot of the target's table */
+  src.register_files(&[fds[0]]).unwrap();
+  dst.register_files_sparse(4).unwrap();
+  dst.set_file_alloc_range(2, 2).unwrap();
+
+  src.msg_ring_fd(dst.as_raw_fd(), 0, 1, 9, 0).unwrap().set_data_u64(3);
+  src.msg_ring_fd(dst.as_raw_fd(), 0, IORING_FILE_INDEX_ALLOC, 10, 0).unwrap().set_data_u64(4);
+  src.msg_ring_fd(dst.as_raw_fd(), 0, 0, 11, IORING_MSG_RING_CQE_SKIP).unwrap().set_data_u64(5);
+
+  assert_eq!(reap(&mut src, 3), vec![(3, 0), (4, 2), (5, 0)]);
+
+  assert_eq!(reap(&mut dst, 2), vec![(9, 0), (10, 2)]);
+
+  unsafe { libc::write(fds[1], b"ping".as_ptr() as *const c_void, 4) };
+
+  dst.read(2, buf.as_mut_ptr() as *mut c_void, 4).unwrap().fixed_file();
+  dst.submit_and_wait(1).unwrap();
+
+  assert_eq!(dst.wait().unwrap().res, 4);
+  assert_eq!(&buf, b"ping");
+
+  unsafe {
+    libc::close(fds[0]);
+    libc::close(fds[1]);
+  };
+}
+
+#[test]
+fn socket_commands() {
+  use std::{ffi::c_void, io::Write, mem::size_of, net::{TcpListener, TcpStream}, os::fd::AsRawFd};
+  use libc::{c_int, socklen_t, IPPROTO_TCP, TCP_NODELAY, SOL_SOCKET, SO_RCVBUF};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+  let (server, _) = listener.accept().unwrap();
+  let len = size_of::<c_int>() as socklen_t;
+
+  client.write_all(b"hello").unwrap();
+
+  /* Loopback delivers at once, so nothing is left unacknowledged */
+  ring.siocinq(server.as_raw_fd()).unwrap().set_data_u64(1);
+  ring.siocoutq(client.as_raw_fd()).unwrap().set_data_u64(2);
+
+  assert_eq!(reap(&mut ring, 2), vec![(1, 5), (2, 0)]);
+
+  let nodelay: c_int = 1;
+  let rcvbuf: c_int = 64 * 1024;
+  let mut value: c_int = 0;
+
+  ring.setsockopt(client.as_raw_fd(), IPPROTO_TCP, TCP_NODELAY, &nodelay as *const c_int as *const c_void, len).unwrap().set_data_u64(3);
+  ring.setsockopt(client.as_raw_fd(), SOL_SOCKET, SO_RCVBUF, &rcvbuf as *const c_int as *const c_void, len).unwrap().set_data_u64(4).link();
+  ring.getsockopt(client.as_raw_fd(), SOL_SOCKET, SO_RCVBUF, &mut value as *mut c_int as *mut c_void, len).unwrap().set_data_u64(5);
+
+  assert_eq!(reap(&mut ring, 3), vec![(3, 0), (4, 0), (5, len as i32)]);
+  assert!(client.nodelay().unwrap());
+
+  /* The kernel doubles SO_RCVBUF for bookkeeping */
+  assert_eq!(value, rcvbuf * 2);
+}
+
+#[test]
+fn uring_cmd_big() {
+  use std::{io::Write, net::{TcpListener, TcpStream}, os::fd::AsRawFd};
+  use rsring::{RSRing128, constants::*};
+
+  let mut ring = RSRing128::new(4).unwrap();
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+  let (server, _) = listener.accept().unwrap();
+
+  client.write_all(b"queued").unwrap();
+
+  /* Enough rounds to wrap both queues, so every slot of the big sqes and cqes is used */
+  for i in 0..12 {
+    ring.nop().unwrap().set_data_u64(i * 2);
+    ring.uring_cmd(server.as_raw_fd(), SOCKET_URING_OP_SIOCINQ, &[0; 80]).unwrap().set_data_u64(i * 2 + 1);
+    ring.submit_and_wait(2).unwrap();
+
+    let results: Vec<(u64, i32, [u8; 16])> = ring.completions().map(|cqe| (cqe.user_data, cqe.res, cqe.big_cqe)).collect();
+
+    assert_eq!(results, vec![(i * 2, 0, [0; 16]), (i * 2 + 1, 6, [0; 16])]);
+  }
+
+  /* Without SQE128 there is no room for the payload */
+  let err = RSRing::new(4).unwrap().uring_cmd(server.as_raw_fd(), SOCKET_URING_OP_SIOCINQ, &[0; 80]).unwrap_err();
+  assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+}
+
+#[test]
+fn path_xattrs() {
+  use std::{cell::RefCell, ffi::{c_void, CString}, os::unix::ffi::OsStrExt, rc::Rc};
+  use libc::ENODATA;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (_file, path) = temp_file("xattr");
+  let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+  let seen = Rc::new(RefCell::new(Vec::new()));
+
+  /* The owned variants keep their buffers alive inside the handler until dispatch */
+  let set = seen.clone();
+
+  ring.setxattr_owned(path.clone(), CString::new("user.rsring").unwrap(), b"backup".to_vec(), 0, move |res| {
+    set.borrow_mut().push(res.map(|_| Vec::new()).map_err(|err| err.raw_os_error()));
+  }).unwrap();
+  ring.submit_and_wait(1).unwrap();
+  ring.dispatch();
+
+  let get = seen.clone();
+
+  ring.getxattr_owned(path.clone(), CString::new("user.rsring").unwrap(), 64, move |res| {
+    get.borrow_mut().push(res.map_err(|err| err.raw_os_error()));
+  }).unwrap();
+
+  let missing = seen.clone();
+
+  ring.getxattr_owned(path.clone(), CString::new("user.missing").unwrap(), 64, move |res| {
+    missing.borrow_mut().push(res.map_err(|err| err.raw_os_error()));
+  }).unwrap();
+  ring.submit_and_wait(2).unwrap();
+  ring.dispatch();
+
+  seen.borrow_mut().sort();
+  assert_eq!(*seen.borrow(), vec![Ok(Vec::new()), Ok(b"backup".to_vec()), Err(Some(ENODATA))]);
+  assert_eq!(ring.dispatch_pending(), 0);
+
+  /* The raw op reports the length when asked with no buffer */
+  let name = CString::new("user.rsring").unwrap();
+
+  ring.getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut::<c_void>(), 0).unwrap();
+  ring.submit_and_wait(1).unwrap();
+
+  assert_eq!(ring.wait().unwrap().res, 6);
+
+  std::fs::remove_file(path.to_str().unwrap()).unwrap();
+}
+
+#[test]
+fn openat2_resolve() {
+  use std::{ffi::CString, fs, os::unix::{ffi::OsStrExt, fs::symlink}};
+  use libc::{O_RDONLY, O_DIRECTORY, EXDEV, ELOOP};
+  use rsring::{open_how, constants::*};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let root = std::env::temp_dir().join(format!("rsring-{}-openat2", std::process::id()));
+
+  let _ = fs::remove_dir_all(&root);
+
+  fs::create_dir_all(&root).unwrap();
+  fs::write(root.join("inside"), b"ok").unwrap();
+  symlink("/etc", root.join("link")).unwrap();
+
+  let root_path = CString::new(root.as_os_str().as_bytes()).unwrap();
+  let dirfd = unsafe { libc::open(root_path.as_ptr(), O_RDONLY | O_DIRECTORY) };
+  let paths = ["inside", "../inside", "link", "/inside"].map(|path| CString::new(path).unwrap());
+  let hows = [
+    open_how::new(O_RDONLY, 0, RESOLVE_BENEATH),
+    open_how::new(O_RDONLY, 0, RESOLVE_BENEATH),
+    open_how::new(O_RDONLY, 0, RESOLVE_NO_SYMLINKS),
+    open_how::new(O_RDONLY, 0, RESOLVE_IN_ROOT),
+  ];
+
+  for (i, (path, how)) in paths.iter().zip(&hows).enumerate() {
+    ring.openat2(dirfd, path.as_ptr(), how).unwrap().set_data_u64(i as u64);
+  }
+
+  let results = reap(&mut ring, 4);
+
+  /* Escaping the root or following a symlink is refused, an absolute path stays inside with IN_ROOT */
+  assert!(results[0].1 >= 0 && results[3].1 >= 0);
+  assert_eq!((results[1].1, results[2].1), (-EXDEV, -ELOOP));
+
+  unsafe {
+    libc::close(results[0].1);
+    libc::close(results[3].1);
+    libc::close(dirfd);
+  };
+  fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn file_position() {
+  use std::{ffi::c_void, fs, io::{Seek, SeekFrom}, os::fd::AsRawFd};
+  use libc::iovec;
+
+  let mut ring = RSRing::new(32).unwrap();
+  let (mut file, path) = temp_file("position");
+  let fd = file.as_raw_fd();
+  let mut head = [0u8; 5];
+  let mut tail = [0u8; 6];
+  let mut word = [0u8; 5];
+
+  /* Plain writes append at the file position, a positioned write leaves it alone */
+  ring.write(fd, b"hello".as_ptr() as *const c_void, 5).unwrap().link();
+  ring.write(fd, b" world".as_ptr() as *const c_void, 6).unwrap().link();
+  ring.pwrite(fd, b"J".as_ptr() as *const c_void, 1, 0).unwrap();
+  ring.submit_and_wait(3).unwrap();
+
+  assert_eq!(ring.completions().map(|cqe| cqe.res).collect::<Vec<i32>>(), vec![5, 6, 1]);
+  assert_eq!(file.stream_position().unwrap(), 11);
+  assert_eq!(fs::read(&path).unwrap(), b"Jello world");
+
+  /* Plain reads continue from the file position, a positioned read does not move it */
+  file.seek(SeekFrom::Start(0)).unwrap();
+
+  let iov = [iovec { iov_base: tail.as_mut_ptr() as *mut c_void, iov_len: tail.len() }];
+
+  ring.read(fd, head.as_mut_ptr() as *mut c_void, 5).unwrap().link();
+  ring.readv(fd, iov.as_ptr(), 1).unwrap().link();
+  ring.pread(fd, word.as_mut_ptr() as *mut c_void, 5, 6).unwrap();
+  ring.submit_and_wait(3).unwrap();
+
+  assert_eq!(ring.completions().map(|cqe| cqe.res).collect::<Vec<i32>>(), vec![5, 6, 5]);
+  assert_eq!((&head, &tail, &word), (b"Jello", b" world", b"world"));
+  assert_eq!(file.stream_position().unwrap(), 11);
+
+  fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn zerocopy_send() {
+  use std::{cell::RefCell, io::{IoSliceMut, Read}, net::{TcpListener, TcpStream}, os::fd::AsRawFd, rc::Rc};
+
+  let mut ring = RSRing::new(32).unwrap();
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+  let (mut server, _) = listener.accept().unwrap();
+  let done = Rc::new(RefCell::new(Vec::new()));
+
+  let mut fixed = b"fixed".to_vec();
+
+  ring.register_buffers(&[IoSliceMut::new(&mut fixed)]).unwrap();
+
+  /* The handlers only run once the notification releases each buffer */
+  let owned = done.clone();
+  let fixed_done = done.clone();
+
+  ring.send_zc_owned(client.as_raw_fd(), b"owned".to_vec(), 0, move |send, buf| owned.borrow_mut().push((send, Some(buf)))).unwrap();
+  ring.send_zc_fixed_notify(client.as_raw_fd(), fixed.as_ptr() as *const _, 5, 0, 0, move |send| fixed_done.borrow_mut().push((send, None))).unwrap();
+
+  let err = ring.send_zc_fixed_notify(client.as_raw_fd(), fixed.as_ptr() as *const _, 6, 0, 0, |_| ()).unwrap_err();
+
+  assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+  ring.submit().unwrap();
+
+  while ring.dispatch_pending() > 0 {
+    ring.submit_and_wait(1).unwrap();
+    ring.dispatch();
+  }
+
+  let mut received = [0u8; 10];
+
+  server.read_exact(&mut received).unwrap();
+
+  assert_eq!(&received, b"ownedfixed");
+
+  /* Loopback delivery always copies the pages */
+  for (send, buf) in done.borrow().iter() {
+    assert_eq!(send.result.as_ref().unwrap(), &5);
+    assert!(send.copied);
+    assert!(buf.as_deref().is_none_or(|buf| buf == b"owned"));
+  }
+  assert_eq!(done.borrow().len(), 2);
+}